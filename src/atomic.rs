@@ -0,0 +1,358 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    state, Chronoflake, Clock, Error, IdGenerator, Layout, RollbackPolicy, ShardLease, StateFile,
+    SystemClock, WaitStrategy,
+};
+
+/// Thread-safe unique ID generator
+///
+/// Produces the same IDs as [`IdGenerator`] but can be shared between threads
+/// without a lock. The last timestamp and sequence are packed into a single
+/// atomic which is advanced with compare-and-swap.
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use chronoflake::{AtomicIdGenerator, IdGenerator};
+///
+/// let cf = Arc::new(AtomicIdGenerator::from(
+///     IdGenerator::new(16).with_epoch(1488432924251),
+/// ));
+///
+/// let handle = {
+///     let cf = Arc::clone(&cf);
+///     std::thread::spawn(move || cf.generate_id().unwrap())
+/// };
+///
+/// let id = cf.generate_id().unwrap();
+/// assert_ne!(id, handle.join().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct AtomicIdGenerator<C = SystemClock> {
+    epoch: u64,
+    shard_id: u16,
+    layout: Layout,
+    wait_strategy: WaitStrategy,
+    rollback_policy: RollbackPolicy,
+    clock: C,
+
+    /// `(timestamp - epoch) << sequence_bits | sequence` of the last issued ID
+    state: AtomicU64,
+
+    /// Only locked when IDs are issued past `reserved`
+    state_file: Option<Mutex<StateFile>>,
+    reserved: AtomicU64,
+
+    /// Held for as long as the generator lives
+    _lease: Option<Arc<ShardLease>>,
+}
+
+impl AtomicIdGenerator {
+    /// Create a new thread-safe ID generator with the default settings
+    ///
+    /// ```rust
+    /// use chronoflake::AtomicIdGenerator;
+    ///
+    /// let cf = AtomicIdGenerator::new(16);
+    /// ```
+    pub fn new(shard_id: u16) -> Self {
+        IdGenerator::new(shard_id).into()
+    }
+}
+
+impl<C: Clock> AtomicIdGenerator<C> {
+    /// Time Epoch in use (in milliseconds)
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Machine or Shard ID
+    pub fn shard_id(&self) -> u16 {
+        self.shard_id
+    }
+
+    /// Bit layout of the generated IDs
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Generate a unique ID
+    ///
+    /// Waits out sequence exhaustion and clock rollback in the same way as
+    /// [`IdGenerator::generate_id`].
+    pub fn generate_id(&self) -> Result<u64, Error> {
+        loop {
+            match self.try_generate_id() {
+                Ok(id) => return Ok(id),
+                Err(err) => state::recover(
+                    &self.clock,
+                    &self.layout,
+                    self.epoch,
+                    self.wait_strategy,
+                    self.rollback_policy,
+                    err,
+                )?,
+            }
+        }
+    }
+
+    /// Generate a unique ID without blocking
+    ///
+    /// Fails in the same way as [`IdGenerator::try_generate_id`].
+    pub fn try_generate_id(&self) -> Result<u64, Error> {
+        let (ts, sequence, _) = self.try_claim(1)?;
+        Ok(self.layout.compose(ts, self.shard_id, sequence))
+    }
+
+    /// Generate `count` unique IDs in increasing order
+    ///
+    /// See [`fill`](Self::fill).
+    pub fn generate_batch(&self, count: usize) -> Result<Vec<u64>, Error> {
+        let mut ids = vec![0; count];
+        self.fill(&mut ids)?;
+        Ok(ids)
+    }
+
+    /// Fill a slice with unique IDs in increasing order
+    ///
+    /// Works like [`IdGenerator::fill`], claiming each tick's range of sequence
+    /// numbers with a single compare-and-swap. Other threads can still generate
+    /// IDs in between ranges, so the batch is only contiguous within each tick.
+    pub fn fill(&self, ids: &mut [u64]) -> Result<(), Error> {
+        let mut filled = 0;
+        while filled < ids.len() {
+            match self.try_claim(ids.len() - filled) {
+                Ok((ts, first, last)) => {
+                    for sequence in first..=last {
+                        ids[filled] = self.layout.compose(ts, self.shard_id, sequence);
+                        filled += 1;
+                    }
+                }
+                Err(err) => state::recover(
+                    &self.clock,
+                    &self.layout,
+                    self.epoch,
+                    self.wait_strategy,
+                    self.rollback_policy,
+                    err,
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Decode an ID generated with this generator's epoch and layout
+    pub fn decode(&self, id: u64) -> Chronoflake {
+        Chronoflake::new(id)
+            .with_epoch(self.epoch)
+            .with_layout(self.layout)
+    }
+
+    /// Claim up to `count` sequence numbers in the current tick, returning the
+    /// tick and the first and last sequence claimed
+    fn try_claim(&self, count: usize) -> Result<(u64, u16, u16), Error> {
+        let mut current = self.state.load(Ordering::Acquire);
+        loop {
+            // Read the clock after the state, and again on every retry, so a
+            // timestamp stored by another thread is never newer than `now`
+            let now = self.clock.now_millis();
+            let (timestamp, sequence) = self.unpack(current);
+            let (timestamp, first, last) = state::advance_by(
+                &self.layout,
+                self.rollback_policy,
+                self.epoch,
+                timestamp,
+                sequence,
+                now,
+                count,
+            )?;
+
+            self.reserve(timestamp)?;
+
+            let next = self.pack(timestamp, last);
+            match self.state.compare_exchange_weak(
+                current,
+                next,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Ok((self.layout.to_ticks(timestamp - self.epoch), first, last)),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+
+    fn reserve(&self, timestamp: u64) -> Result<(), Error> {
+        if timestamp <= self.reserved.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let Some(state_file) = &self.state_file else {
+            return Ok(());
+        };
+
+        let mut state_file = state_file.lock().unwrap_or_else(|e| e.into_inner());
+        state_file.reserve(timestamp)?;
+        self.reserved
+            .store(state_file.reserved(), Ordering::Release);
+        Ok(())
+    }
+
+    fn pack(&self, timestamp: u64, sequence: u16) -> u64 {
+        let ticks = self.layout.to_ticks(timestamp - self.epoch);
+        (ticks << self.layout.sequence_bits()) | sequence as u64
+    }
+
+    fn unpack(&self, state: u64) -> (u64, u16) {
+        let ticks = state >> self.layout.sequence_bits();
+        let timestamp = self.epoch + self.layout.to_millis(ticks);
+        (timestamp, state as u16 & self.layout.max_sequence())
+    }
+}
+
+impl<C: Clock> From<IdGenerator<C>> for AtomicIdGenerator<C> {
+    /// Share an [`IdGenerator`] between threads, keeping its settings and last issued ID
+    fn from(cf: IdGenerator<C>) -> Self {
+        let generator = Self {
+            epoch: cf.epoch,
+            shard_id: cf.shard_id,
+            layout: cf.layout,
+            wait_strategy: cf.wait_strategy,
+            rollback_policy: cf.rollback_policy,
+            clock: cf.clock,
+            state: AtomicU64::new(0),
+            reserved: AtomicU64::new(cf.state_file.as_ref().map_or(u64::MAX, |f| f.reserved())),
+            state_file: cf.state_file.map(Mutex::new),
+            _lease: cf.lease,
+        };
+
+        let state = generator.pack(cf.timestamp.max(cf.epoch), cf.sequence);
+        generator.state.store(state, Ordering::Relaxed);
+        generator
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use crate::{MockClock, DEFAULT_EPOCH};
+
+    #[test]
+    fn is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<AtomicIdGenerator>();
+    }
+
+    #[test]
+    fn concurrent_unique() {
+        let cf = Arc::new(AtomicIdGenerator::new(49));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let cf = Arc::clone(&cf);
+                std::thread::spawn(move || {
+                    (0..50_000)
+                        .map(|_| cf.generate_id().unwrap())
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        let mut ids = HashSet::new();
+        for handle in handles {
+            let thread_ids = handle.join().unwrap();
+            assert!(thread_ids.windows(2).all(|w| w[0] < w[1]));
+            ids.extend(thread_ids);
+        }
+
+        assert_eq!(ids.len(), 8 * 50_000);
+    }
+
+    #[test]
+    fn concurrent_without_spurious_rollbacks() {
+        let cf = Arc::new(AtomicIdGenerator::from(
+            IdGenerator::new(49).with_rollback_policy(RollbackPolicy::Error),
+        ));
+
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let cf = Arc::clone(&cf);
+                std::thread::spawn(move || {
+                    (0..50_000)
+                        .map(|_| cf.generate_id())
+                        .collect::<Result<Vec<_>, _>>()
+                })
+            })
+            .collect();
+
+        let mut ids = HashSet::new();
+        for handle in handles {
+            ids.extend(handle.join().unwrap().unwrap());
+        }
+
+        assert_eq!(ids.len(), 8 * 50_000);
+    }
+
+    #[test]
+    fn concurrent_batches() {
+        let cf = Arc::new(AtomicIdGenerator::new(49));
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let cf = Arc::clone(&cf);
+                std::thread::spawn(move || {
+                    let mut ids = Vec::new();
+                    for _ in 0..20 {
+                        let batch = cf.generate_batch(2_500).unwrap();
+                        assert!(batch.windows(2).all(|w| w[0] < w[1]));
+                        ids.extend(batch);
+                        ids.push(cf.generate_id().unwrap());
+                    }
+                    ids
+                })
+            })
+            .collect();
+
+        let mut ids = HashSet::new();
+        for handle in handles {
+            ids.extend(handle.join().unwrap());
+        }
+
+        assert_eq!(ids.len(), 4 * 20 * 2_501);
+    }
+
+    #[test]
+    fn exhaustion_with_mock_clock() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let cf = AtomicIdGenerator::from(IdGenerator::new(49).with_clock(clock.clone()));
+
+        let max = cf.layout().max_sequence() as usize;
+        let ids: HashSet<u64> = (0..=max).map(|_| cf.try_generate_id().unwrap()).collect();
+        assert_eq!(ids.len(), max + 1);
+        assert_eq!(cf.try_generate_id(), Err(Error::SequenceExhausted));
+
+        let id = cf.decode(cf.generate_id().unwrap());
+        assert_eq!(id.timestamp(), DEFAULT_EPOCH + 1001);
+        assert_eq!(id.sequence(), 0);
+    }
+
+    #[test]
+    fn matches_id_generator_format() {
+        let layout = Layout::new(39, 14, 10).unwrap();
+        let cf = IdGenerator::new(9000)
+            .with_epoch(1488432924251)
+            .with_layout(layout);
+        let atomic = AtomicIdGenerator::from(cf.clone());
+
+        let before = SystemClock.now_millis();
+        let id = atomic.decode(atomic.generate_id().unwrap());
+        assert_eq!(id, cf.decode(id.id()));
+        assert_eq!(id.shard_id(), 9000);
+        assert!(id.timestamp() >= before);
+    }
+}
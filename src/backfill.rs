@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use chrono::{DateTime, Utc};
+
+use crate::{Error, Layout, Preset, DEFAULT_EPOCH};
+
+/// Generator of IDs for past timestamps, for importing existing records
+///
+/// Each ID embeds the timestamp it is generated for rather than the current time,
+/// so imported rows keep their place in ID order and in [`IdRange`](crate::IdRange)
+/// queries. IDs are issued from a range of shard IDs reserved for backfilling:
+/// as long as no live generator uses those shards, backfilled IDs can't collide
+/// with live ones, which [`IdGeneratorBuilder::with_reserved_shards`](crate::IdGeneratorBuilder::with_reserved_shards)
+/// enforces.
+///
+/// Sequence numbers are tracked per timestamp, moving on to the next reserved
+/// shard once one is used up, so each tick holds as many IDs as the reserved
+/// shards have sequence numbers. The generator keeps one entry per distinct
+/// timestamp it has seen.
+///
+/// ```rust
+/// use chronoflake::{BackfillGenerator, IdGenerator};
+///
+/// const EPOCH: u64 = 1488432924251;
+/// let mut backfill = BackfillGenerator::new(1020..=1023).with_epoch(EPOCH);
+///
+/// let created_at = 1500000000000;
+/// let first = backfill.generate_id(created_at).unwrap();
+/// let second = backfill.generate_id(created_at).unwrap();
+/// assert_ne!(first, second);
+///
+/// let cf = IdGenerator::new(14).with_epoch(EPOCH);
+/// assert_eq!(cf.decode(first).timestamp(), created_at);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BackfillGenerator {
+    epoch: u64,
+    layout: Layout,
+    shards: RangeInclusive<u16>,
+
+    /// Shard and sequence of the last ID issued at each tick since the epoch
+    issued: HashMap<u64, (u16, u16)>,
+}
+
+impl BackfillGenerator {
+    /// Create a backfill generator using the reserved shard IDs, with the default
+    /// epoch and layout
+    pub fn new(shards: RangeInclusive<u16>) -> Self {
+        Self {
+            epoch: DEFAULT_EPOCH,
+            layout: Layout::DEFAULT,
+            shards,
+            issued: HashMap::new(),
+        }
+    }
+
+    /// Set the epoch (in milliseconds) of the live generators
+    pub fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Set the bit layout of the live generators
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Use the epoch and layout of a well-known ID scheme
+    pub fn with_preset(self, preset: Preset) -> Self {
+        self.with_epoch(preset.epoch).with_layout(preset.layout)
+    }
+
+    /// The shard IDs reserved for backfilling
+    pub fn shards(&self) -> &RangeInclusive<u16> {
+        &self.shards
+    }
+
+    /// Generate an ID for a Unix timestamp (in milliseconds)
+    ///
+    /// With ticks longer than a millisecond the timestamp is rounded down to the
+    /// start of its tick, as for live IDs. Fails with [`Error::TimestampBeforeEpoch`]
+    /// or [`Error::TimestampOverflow`] if the layout can't hold the timestamp, and
+    /// with [`Error::BackfillExhausted`] once every reserved shard has used up its
+    /// sequence numbers for the tick.
+    pub fn generate_id(&mut self, timestamp: u64) -> Result<u64, Error> {
+        self.validate()?;
+        if timestamp < self.epoch {
+            return Err(Error::TimestampBeforeEpoch);
+        }
+
+        let ticks = self.layout.to_ticks(timestamp - self.epoch);
+        if ticks > self.layout.max_timestamp() {
+            return Err(Error::TimestampOverflow {
+                max: self.layout.max_timestamp(),
+            });
+        }
+
+        let (shard_id, sequence) = match self.issued.get(&ticks) {
+            None => (*self.shards.start(), 0),
+            Some(&(shard_id, sequence)) if sequence < self.layout.max_sequence() => {
+                (shard_id, sequence + 1)
+            }
+            Some(&(shard_id, _)) if shard_id < *self.shards.end() => (shard_id + 1, 0),
+            Some(_) => return Err(Error::BackfillExhausted { timestamp }),
+        };
+
+        self.issued.insert(ticks, (shard_id, sequence));
+        Ok(self.layout.compose(ticks, shard_id, sequence))
+    }
+
+    /// Generate an ID for a point in time
+    ///
+    /// See [`generate_id`](Self::generate_id). Times before the Unix epoch fail
+    /// with [`Error::TimestampBeforeEpoch`].
+    pub fn generate_id_at(&mut self, datetime: DateTime<Utc>) -> Result<u64, Error> {
+        let timestamp =
+            u64::try_from(datetime.timestamp_millis()).map_err(|_| Error::TimestampBeforeEpoch)?;
+        self.generate_id(timestamp)
+    }
+
+    /// Check that the reserved shards are valid for the layout
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.shards.is_empty() {
+            return Err(Error::EmptyShardRange);
+        }
+
+        let max = self.layout.max_shard_id();
+        if *self.shards.end() > max {
+            return Err(Error::ShardIdTooLarge {
+                shard_id: *self.shards.end(),
+                max,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chronoflake, IdGenerator, MockClock};
+    use chrono::TimeZone;
+    use std::collections::HashSet;
+
+    #[test]
+    fn ids_embed_the_given_timestamp() {
+        let mut backfill = BackfillGenerator::new(1023..=1023);
+        let timestamps = [
+            DEFAULT_EPOCH,
+            DEFAULT_EPOCH + 1,
+            1500000000123,
+            1400000000000,
+        ];
+
+        for timestamp in timestamps {
+            let id = Chronoflake::new(backfill.generate_id(timestamp).unwrap());
+            assert_eq!(id.timestamp(), timestamp);
+            assert_eq!(id.shard_id(), 1023);
+            assert_eq!(id.sequence(), 0);
+        }
+
+        let datetime = Utc.with_ymd_and_hms(2015, 6, 1, 12, 0, 0).unwrap();
+        let id = backfill.generate_id_at(datetime).unwrap();
+        assert_eq!(Chronoflake::new(id).datetime(), datetime);
+
+        assert_eq!(
+            backfill.generate_id(DEFAULT_EPOCH - 1),
+            Err(Error::TimestampBeforeEpoch)
+        );
+
+        let datetime = Utc.with_ymd_and_hms(1969, 12, 31, 23, 59, 59).unwrap();
+        assert_eq!(
+            backfill.generate_id_at(datetime),
+            Err(Error::TimestampBeforeEpoch)
+        );
+    }
+
+    #[test]
+    fn spills_over_reserved_shards() {
+        let layout = Layout::new(51, 2, 10).unwrap();
+        let mut backfill = BackfillGenerator::new(2..=3).with_layout(layout);
+
+        let ids: Vec<u64> = (0..2048)
+            .map(|_| backfill.generate_id(1500000000000).unwrap())
+            .collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(layout.shard_id(ids[1023]), 2);
+        assert_eq!(layout.shard_id(ids[1024]), 3);
+
+        assert_eq!(
+            backfill.generate_id(1500000000000),
+            Err(Error::BackfillExhausted {
+                timestamp: 1500000000000
+            })
+        );
+        assert!(backfill.generate_id(1500000000001).is_ok());
+    }
+
+    #[test]
+    fn never_collides_with_live_ids() {
+        let now = DEFAULT_EPOCH + 10_000;
+        let mut cf = IdGenerator::builder()
+            .with_shard_id(0)
+            .with_reserved_shards(1..=1)
+            .with_clock(MockClock::new(now))
+            .build()
+            .unwrap();
+        let mut backfill = BackfillGenerator::new(1..=1);
+
+        let live: HashSet<u64> = cf.generate_batch(4096).unwrap().into_iter().collect();
+        assert!((0..4096).all(|_| !live.contains(&backfill.generate_id(now).unwrap())));
+    }
+
+    #[test]
+    fn rejects_invalid_shards() {
+        let mut backfill = BackfillGenerator::new(RangeInclusive::new(3, 2));
+        assert_eq!(
+            backfill.generate_id(DEFAULT_EPOCH),
+            Err(Error::EmptyShardRange)
+        );
+
+        let mut backfill = BackfillGenerator::new(1000..=1024);
+        assert_eq!(
+            backfill.generate_id(DEFAULT_EPOCH),
+            Err(Error::ShardIdTooLarge {
+                shard_id: 1024,
+                max: 1023
+            })
+        );
+    }
+}
@@ -0,0 +1,428 @@
+//! Command-line tool for generating, decoding and converting Chronoflake IDs
+use std::io::{self, BufRead, Write};
+use std::process::ExitCode;
+
+use chronoflake::{Chronoflake, IdGenerator, IdGeneratorBuilder, Layout, Preset, ShardSource};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+#[derive(Parser)]
+#[command(version, about = "Generate, decode and convert Chronoflake IDs")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate new IDs
+    Generate {
+        #[command(flatten)]
+        shard: Shard,
+
+        /// Number of IDs to generate
+        #[arg(long, default_value_t = 1)]
+        count: usize,
+
+        #[command(flatten)]
+        scheme: Scheme,
+    },
+
+    /// Decode IDs into their timestamp, shard and sequence
+    Decode {
+        /// IDs to decode, read from stdin if none are given
+        ids: Vec<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
+
+        #[command(flatten)]
+        scheme: Scheme,
+    },
+
+    /// Re-encode IDs with another epoch and layout
+    Convert {
+        /// IDs to convert, read from stdin if none are given
+        ids: Vec<String>,
+
+        #[command(flatten)]
+        scheme: Scheme,
+
+        #[command(flatten)]
+        target: Target,
+    },
+
+    /// Serve IDs over HTTP
+    #[cfg(feature = "http")]
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        listen: String,
+
+        #[command(flatten)]
+        shard: Shard,
+
+        #[command(flatten)]
+        scheme: Scheme,
+    },
+}
+
+/// Where the shard ID of new IDs comes from
+#[derive(Args)]
+#[group(required = true, multiple = false)]
+struct Shard {
+    /// Machine or Shard ID
+    #[arg(long)]
+    shard: Option<u16>,
+
+    /// Find the shard ID from `env:NAME`, `hostname`, `ipv4`, `machine-id` or `machine-id-hashed`
+    #[arg(long)]
+    shard_from: Option<ShardSource>,
+}
+
+impl Shard {
+    fn builder(self, scheme: &Scheme) -> IdGeneratorBuilder {
+        let builder = IdGenerator::builder()
+            .with_epoch(scheme.epoch())
+            .with_layout(scheme.layout());
+
+        match (self.shard, self.shard_from) {
+            (Some(shard), _) => builder.with_shard_id(shard),
+            (None, Some(source)) => builder.with_shard_source(source),
+            (None, None) => builder,
+        }
+    }
+}
+
+/// Options describing how IDs are laid out
+#[derive(Args)]
+struct Scheme {
+    /// Well-known scheme to take the epoch and layout from
+    #[arg(long, default_value_t = Preset::TWITTER)]
+    preset: Preset,
+
+    /// Epoch the IDs are relative to (Unix milliseconds) [default: from preset]
+    #[arg(long)]
+    epoch: Option<u64>,
+
+    /// Bit widths of the timestamp, shard and sequence fields [default: from preset]
+    #[arg(long)]
+    layout: Option<Layout>,
+}
+
+impl Scheme {
+    fn epoch(&self) -> u64 {
+        self.epoch.unwrap_or(self.preset.epoch)
+    }
+
+    fn layout(&self) -> Layout {
+        self.layout.unwrap_or(self.preset.layout)
+    }
+}
+
+/// Options describing the layout to convert IDs to, defaulting to the source's
+#[derive(Args)]
+struct Target {
+    /// Well-known scheme to take the target epoch and layout from
+    #[arg(long)]
+    to_preset: Option<Preset>,
+
+    /// Epoch to convert to (Unix milliseconds)
+    #[arg(long)]
+    to_epoch: Option<u64>,
+
+    /// Bit widths to convert to
+    #[arg(long)]
+    to_layout: Option<Layout>,
+}
+
+impl Target {
+    fn epoch(&self, source: &Scheme) -> u64 {
+        self.to_epoch
+            .or(self.to_preset.map(|preset| preset.epoch))
+            .unwrap_or(source.epoch())
+    }
+
+    fn layout(&self, source: &Scheme) -> Layout {
+        self.to_layout
+            .or(self.to_preset.map(|preset| preset.layout))
+            .unwrap_or(source.layout())
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Generate {
+            shard,
+            count,
+            scheme,
+        } => generate(shard, count, &scheme),
+        Command::Decode {
+            ids,
+            format,
+            scheme,
+        } => decode(ids, format, &scheme),
+        Command::Convert {
+            ids,
+            scheme,
+            target,
+        } => convert(ids, &scheme, &target),
+        #[cfg(feature = "http")]
+        Command::Serve {
+            listen,
+            shard,
+            scheme,
+        } => serve(&listen, shard, &scheme),
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("chronoflake: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn generate(
+    shard: Shard,
+    count: usize,
+    scheme: &Scheme,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut cf = shard.builder(scheme).build()?;
+
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    for _ in 0..count {
+        writeln!(out, "{}", cf.generate_id()?)?;
+    }
+    out.flush()?;
+
+    Ok(true)
+}
+
+#[cfg(feature = "http")]
+fn serve(listen: &str, shard: Shard, scheme: &Scheme) -> Result<bool, Box<dyn std::error::Error>> {
+    let cf = shard.builder(scheme).build()?;
+    let server = chronoflake::http::Server::bind(listen, cf.into())?;
+    eprintln!(
+        "chronoflake: serving shard {} on http://{}",
+        server.generator().shard_id(),
+        server.local_addr()
+    );
+    server.run();
+
+    Ok(true)
+}
+
+/// Decode each ID, reporting the ones that can't be parsed and carrying on
+fn decode(
+    ids: Vec<String>,
+    format: Format,
+    scheme: &Scheme,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let ok = for_each_id(ids, |raw| decode_one(&mut out, raw, format, scheme))?;
+    out.flush()?;
+
+    Ok(ok)
+}
+
+fn decode_one(
+    out: &mut impl Write,
+    raw: &str,
+    format: Format,
+    scheme: &Scheme,
+) -> io::Result<bool> {
+    match parse(raw, scheme) {
+        Ok(id) => writeln!(out, "{}", render(&id, format)).map(|_| true),
+        Err(e) => {
+            eprintln!("chronoflake: {raw:?}: {e}");
+            Ok(false)
+        }
+    }
+}
+
+/// Convert each ID, reporting the ones that can't be parsed or don't fit and carrying on
+fn convert(
+    ids: Vec<String>,
+    scheme: &Scheme,
+    target: &Target,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    let mut out = io::BufWriter::new(io::stdout().lock());
+    let ok = for_each_id(ids, |raw| convert_one(&mut out, raw, scheme, target))?;
+    out.flush()?;
+
+    Ok(ok)
+}
+
+/// Run `f` on the IDs given as arguments, or on those read from stdin if there
+/// are none, returning whether it succeeded for all of them
+fn for_each_id(ids: Vec<String>, mut f: impl FnMut(&str) -> io::Result<bool>) -> io::Result<bool> {
+    let mut ok = true;
+    if ids.is_empty() {
+        for line in io::stdin().lock().lines() {
+            for raw in line?.split_whitespace() {
+                ok &= f(raw)?;
+            }
+        }
+    } else {
+        for raw in &ids {
+            ok &= f(raw)?;
+        }
+    }
+
+    Ok(ok)
+}
+
+fn convert_one(
+    out: &mut impl Write,
+    raw: &str,
+    scheme: &Scheme,
+    target: &Target,
+) -> io::Result<bool> {
+    let converted = parse(raw, scheme)
+        .and_then(|id| Ok(id.convert(target.epoch(scheme), target.layout(scheme))?));
+
+    match converted {
+        Ok(id) => writeln!(out, "{id}").map(|_| true),
+        Err(e) => {
+            eprintln!("chronoflake: {raw:?}: {e}");
+            Ok(false)
+        }
+    }
+}
+
+fn parse(raw: &str, scheme: &Scheme) -> Result<Chronoflake, Box<dyn std::error::Error>> {
+    let layout = scheme.layout();
+    let id = raw
+        .parse::<Chronoflake>()?
+        .with_epoch(scheme.epoch())
+        .with_layout(layout);
+
+    // Decoding needs the timestamp as a date, so check the ID can be given one
+    id.try_datetime()?;
+
+    Ok(id)
+}
+
+fn render(id: &Chronoflake, format: Format) -> String {
+    let datetime = id
+        .datetime()
+        .to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+    match format {
+        Format::Text => format!(
+            "{id}\ttimestamp={} datetime={datetime} shard={} sequence={}",
+            id.timestamp(),
+            id.shard_id(),
+            id.sequence()
+        ),
+        Format::Json => format!(
+            r#"{{"id":"{id}","timestamp":{},"datetime":"{datetime}","shard_id":{},"sequence":{}}}"#,
+            id.timestamp(),
+            id.shard_id(),
+            id.sequence()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_formats() {
+        let scheme = Scheme {
+            preset: Preset::TWITTER,
+            epoch: Some(1000),
+            layout: None,
+        };
+        let id = parse(&((1234u64 << 22) | (49 << 12) | 7).to_string(), &scheme).unwrap();
+
+        assert_eq!(
+            render(&id, Format::Text),
+            "5175971847\ttimestamp=2234 datetime=1970-01-01T00:00:02.234Z shard=49 sequence=7"
+        );
+        assert_eq!(
+            render(&id, Format::Json),
+            r#"{"id":"5175971847","timestamp":2234,"datetime":"1970-01-01T00:00:02.234Z","shard_id":49,"sequence":7}"#
+        );
+    }
+
+    #[test]
+    fn rejects_ids_outside_layout() {
+        let scheme = Scheme {
+            preset: Preset::TWITTER,
+            epoch: None,
+            layout: None,
+        };
+        assert!(parse("not-an-id", &scheme).is_err());
+        assert!(parse(&u64::MAX.to_string(), &scheme).is_err());
+
+        let scheme = Scheme {
+            preset: Preset::INSTAGRAM,
+            epoch: None,
+            layout: None,
+        };
+        assert!(parse(&(1u64 << 63).to_string(), &scheme).is_err());
+    }
+
+    #[test]
+    fn reports_dates_out_of_range() {
+        let scheme = Scheme {
+            preset: Preset::TWITTER,
+            epoch: Some(9000000000000000),
+            layout: None,
+        };
+        let mut out = Vec::new();
+        assert!(!decode_one(&mut out, "1704967240656416804", Format::Text, &scheme).unwrap());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn target_defaults_to_source() {
+        let scheme = Scheme {
+            preset: Preset::TWITTER,
+            epoch: Some(1000),
+            layout: None,
+        };
+        let target = Target {
+            to_preset: None,
+            to_epoch: None,
+            to_layout: Some(Layout::new(39, 14, 10).unwrap()),
+        };
+        assert_eq!(target.epoch(&scheme), 1000);
+        assert_eq!(target.layout(&scheme), Layout::new(39, 14, 10).unwrap());
+
+        let target = Target {
+            to_preset: Some(Preset::INSTAGRAM),
+            to_epoch: Some(2000),
+            to_layout: None,
+        };
+        assert_eq!(target.epoch(&scheme), 2000);
+        assert_eq!(target.layout(&scheme), Preset::INSTAGRAM.layout);
+
+        let mut out = Vec::new();
+        let raw = ((1234u64 << 22) | (49 << 12) | 7).to_string();
+        assert!(convert_one(&mut out, &raw, &scheme, &target).unwrap());
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!("{}\n", (234u64 << 23) | (49 << 10) | 7)
+        );
+        assert!(!convert_one(&mut Vec::new(), "nope", &scheme, &target).unwrap());
+    }
+
+    #[test]
+    fn cli_is_valid() {
+        use clap::CommandFactory;
+        Cli::command().debug_assert();
+    }
+}
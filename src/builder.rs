@@ -0,0 +1,320 @@
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::{
+    Clock, Error, IdGenerator, Layout, Preset, RollbackPolicy, ShardLease, ShardSource, StateFile,
+    SystemClock, WaitStrategy, DEFAULT_EPOCH,
+};
+
+/// Builder for an [`IdGenerator`] that checks its settings before use
+///
+/// Unlike [`IdGenerator::new`], which silently truncates a shard ID that does not
+/// fit the layout, [`build`](Self::build) returns an error describing what is wrong.
+///
+/// ```rust
+/// use chronoflake::{IdGenerator, Layout};
+///
+/// let cf = IdGenerator::builder()
+///     .with_shard_id(9000)
+///     .with_epoch(1488432924251)
+///     .with_layout(Layout::new(39, 14, 10).unwrap())
+///     .build()
+///     .unwrap();
+///
+/// assert!(IdGenerator::builder().with_shard_id(1024).build().is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct IdGeneratorBuilder<C = SystemClock> {
+    shard: Option<Shard>,
+    epoch: u64,
+    layout: Layout,
+    wait_strategy: WaitStrategy,
+    rollback_policy: RollbackPolicy,
+    clock: C,
+    state_file: Option<StateFile>,
+    reserved: Option<RangeInclusive<u16>>,
+}
+
+impl IdGeneratorBuilder {
+    /// Create a builder with the default settings and no shard ID
+    pub fn new() -> Self {
+        Self {
+            shard: None,
+            epoch: DEFAULT_EPOCH,
+            layout: Layout::default(),
+            wait_strategy: WaitStrategy::default(),
+            rollback_policy: RollbackPolicy::default(),
+            clock: SystemClock,
+            state_file: None,
+            reserved: None,
+        }
+    }
+}
+
+impl Default for IdGeneratorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> IdGeneratorBuilder<C> {
+    /// Set the Machine or Shard ID
+    ///
+    /// A shard ID is required, either from this, [`with_shard_source`](Self::with_shard_source)
+    /// or [`with_shard_lease`](Self::with_shard_lease). Whichever is called last takes effect.
+    pub fn with_shard_id(mut self, shard_id: u16) -> Self {
+        self.shard = Some(Shard::Id(shard_id));
+        self
+    }
+
+    /// Look up the Machine or Shard ID from the environment when the generator is built
+    pub fn with_shard_source(mut self, shard_source: ShardSource) -> Self {
+        self.shard = Some(Shard::Source(shard_source));
+        self
+    }
+
+    /// Lease a free Machine or Shard ID from a directory of lock files when the generator is built
+    ///
+    /// The generator holds the lease until it is dropped, see [`ShardLease`].
+    pub fn with_shard_lease(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.shard = Some(Shard::Lease(dir.into()));
+        self
+    }
+
+    /// Set the epoch (in milliseconds)
+    pub fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Set the bit layout of the generated IDs
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Use the epoch and layout of a well-known ID scheme
+    pub fn with_preset(self, preset: Preset) -> Self {
+        self.with_epoch(preset.epoch).with_layout(preset.layout)
+    }
+
+    /// Set how the generator waits when the sequence is exhausted
+    pub fn with_wait_strategy(mut self, wait_strategy: WaitStrategy) -> Self {
+        self.wait_strategy = wait_strategy;
+        self
+    }
+
+    /// Set how the generator reacts when the clock goes backwards
+    pub fn with_rollback_policy(mut self, rollback_policy: RollbackPolicy) -> Self {
+        self.rollback_policy = rollback_policy;
+        self
+    }
+
+    /// Persist the high-water timestamp to a file so restarts cannot reissue IDs
+    ///
+    /// The file is read when the generator is built, see [`StateFile`].
+    pub fn with_state_file(mut self, state_file: StateFile) -> Self {
+        self.state_file = Some(state_file);
+        self
+    }
+
+    /// Keep the generator off shard IDs reserved for a [`BackfillGenerator`](crate::BackfillGenerator)
+    ///
+    /// Building fails with [`Error::ShardReserved`] if the shard ID is in the range,
+    /// and [`with_shard_lease`](Self::with_shard_lease) skips the range.
+    pub fn with_reserved_shards(mut self, shards: RangeInclusive<u16>) -> Self {
+        self.reserved = Some(shards);
+        self
+    }
+
+    /// Use a different source of time for the generator
+    pub fn with_clock<D: Clock>(self, clock: D) -> IdGeneratorBuilder<D> {
+        IdGeneratorBuilder {
+            shard: self.shard,
+            epoch: self.epoch,
+            layout: self.layout,
+            wait_strategy: self.wait_strategy,
+            rollback_policy: self.rollback_policy,
+            clock,
+            state_file: self.state_file,
+            reserved: self.reserved,
+        }
+    }
+
+    /// Check the settings and create the generator
+    pub fn build(self) -> Result<IdGenerator<C>, Error> {
+        let (shard_id, lease) = match self.shard.ok_or(Error::MissingShardId)? {
+            Shard::Id(shard_id) => (shard_id, None),
+            Shard::Source(source) => (source.resolve(&self.layout)?, None),
+            Shard::Lease(dir) => {
+                let lease =
+                    ShardLease::acquire_excluding(&dir, &self.layout, self.reserved.as_ref())?;
+                (lease.shard_id(), Some(Arc::new(lease)))
+            }
+        };
+        if self
+            .reserved
+            .is_some_and(|reserved| reserved.contains(&shard_id))
+        {
+            return Err(Error::ShardReserved { shard_id });
+        }
+
+        let mut cf = IdGenerator::new(shard_id)
+            .with_epoch(self.epoch)
+            .with_layout(self.layout)
+            .with_wait_strategy(self.wait_strategy)
+            .with_rollback_policy(self.rollback_policy)
+            .with_clock(self.clock);
+        cf.state_file = self.state_file;
+        cf.lease = lease;
+
+        cf.validate()?;
+        cf.restore()?;
+        Ok(cf)
+    }
+}
+
+/// Where the builder gets the shard ID from
+#[derive(Clone, Debug)]
+enum Shard {
+    Id(u16),
+    Source(ShardSource),
+    Lease(PathBuf),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempPath;
+    use crate::{AtomicIdGenerator, MockClock};
+
+    #[test]
+    fn rejects_shard_outside_layout() {
+        let err = IdGenerator::builder()
+            .with_shard_id(1024)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::ShardIdTooLarge {
+                shard_id: 1024,
+                max: 1023
+            }
+        );
+
+        let layout = Layout::new(39, 14, 10).unwrap();
+        let cf = IdGenerator::builder()
+            .with_shard_id(1024)
+            .with_layout(layout)
+            .build()
+            .unwrap();
+        assert_eq!(cf.shard_id, 1024);
+
+        let err = IdGenerator::builder().build().unwrap_err();
+        assert_eq!(err, Error::MissingShardId);
+    }
+
+    #[test]
+    fn resolves_shard_source() {
+        std::env::set_var("CHRONOFLAKE_TEST_BUILDER_SHARD", "600");
+        let source = ShardSource::env("CHRONOFLAKE_TEST_BUILDER_SHARD");
+
+        let cf = IdGenerator::builder()
+            .with_shard_id(1)
+            .with_shard_source(source.clone())
+            .build()
+            .unwrap();
+        assert_eq!(cf.shard_id, 600);
+
+        let err = IdGenerator::builder()
+            .with_shard_source(source.clone())
+            .with_layout(Layout::new(43, 8, 12).unwrap())
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::ShardIdTooLarge {
+                shard_id: 600,
+                max: 255
+            }
+        );
+
+        let cf = IdGenerator::builder()
+            .with_shard_source(source)
+            .with_shard_id(2)
+            .build()
+            .unwrap();
+        assert_eq!(cf.shard_id, 2);
+    }
+
+    #[test]
+    fn leases_shard_id() {
+        let dir = TempPath::new("builder.leases");
+        let builder = IdGenerator::builder()
+            .with_shard_lease(&*dir)
+            .with_layout(Layout::new(46, 1, 16).unwrap());
+
+        let first = builder.clone().build().unwrap();
+        let second = AtomicIdGenerator::from(builder.clone().build().unwrap());
+        assert_eq!(first.shard_id, 0);
+        assert_eq!(second.shard_id(), 1);
+        assert_eq!(
+            builder.clone().build().unwrap_err(),
+            Error::NoFreeShard { max: 1 }
+        );
+
+        // Clones share the lease, which is released when the last one is dropped
+        let clone = first.clone();
+        drop(first);
+        assert!(builder.clone().build().is_err());
+        drop(clone);
+        assert_eq!(builder.build().unwrap().shard_id, 0);
+    }
+
+    #[test]
+    fn keeps_off_reserved_shards() {
+        let builder = IdGenerator::builder().with_reserved_shards(0..=9);
+        assert_eq!(
+            builder.clone().with_shard_id(9).build().unwrap_err(),
+            Error::ShardReserved { shard_id: 9 }
+        );
+        assert!(builder.clone().with_shard_id(10).build().is_ok());
+
+        let dir = TempPath::new("builder-reserved.leases");
+        let cf = builder.with_shard_lease(&*dir).build().unwrap();
+        assert_eq!(cf.shard_id, 10);
+    }
+
+    #[test]
+    fn rejects_bad_epochs() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let builder = IdGenerator::builder()
+            .with_shard_id(49)
+            .with_clock(clock.clone());
+
+        let err = builder
+            .clone()
+            .with_epoch(DEFAULT_EPOCH + 2000)
+            .build()
+            .unwrap_err();
+        assert_eq!(
+            err,
+            Error::EpochInFuture {
+                epoch: DEFAULT_EPOCH + 2000
+            }
+        );
+
+        let layout = Layout::new(31, 16, 16).unwrap();
+        clock.set(DEFAULT_EPOCH + layout.max_timestamp() + 1);
+        let err = builder.clone().with_layout(layout).build().unwrap_err();
+        assert_eq!(
+            err,
+            Error::TimestampOverflow {
+                max: layout.max_timestamp()
+            }
+        );
+
+        assert!(builder.build().is_ok());
+    }
+}
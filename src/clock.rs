@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+
+use crate::WaitStrategy;
+
+/// Source of the current time for the generators
+///
+/// Waiting goes through the clock as well so that simulated clocks can move time
+/// forward instead of blocking.
+pub trait Clock {
+    /// Current Unix timestamp (in milliseconds)
+    fn now_millis(&self) -> u64;
+
+    /// Pause between clock reads while waiting for the next millisecond
+    fn wait(&self, strategy: WaitStrategy) {
+        match strategy {
+            WaitStrategy::Spin => std::hint::spin_loop(),
+            WaitStrategy::Yield => std::thread::yield_now(),
+            WaitStrategy::Sleep(duration) => self.sleep(duration),
+        }
+    }
+
+    /// Block for the given duration
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// The system wall clock
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        Utc::now().timestamp_millis() as u64
+    }
+}
+
+/// Manually controlled clock for tests and simulations
+///
+/// Clones share the same time, so one handle can be given to a generator while
+/// another is used to move time around. Waiting and sleeping advance the clock
+/// rather than blocking.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use chronoflake::{IdGenerator, MockClock, DEFAULT_EPOCH};
+///
+/// let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+/// let mut cf = IdGenerator::new(16).with_clock(clock.clone());
+///
+/// let first = cf.generate_id().unwrap();
+/// clock.advance(Duration::from_secs(60));
+/// let second = cf.generate_id().unwrap();
+///
+/// let elapsed = cf.decode(second).timestamp() - cf.decode(first).timestamp();
+/// assert_eq!(elapsed, 60_000);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MockClock {
+    now: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// Create a clock stopped at the given Unix timestamp (in milliseconds)
+    pub fn new(millis: u64) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(millis)),
+        }
+    }
+
+    /// Move the clock to the given Unix timestamp (in milliseconds), forwards or backwards
+    pub fn set(&self, millis: u64) {
+        self.now.store(millis, Ordering::SeqCst);
+    }
+
+    /// Move the clock forwards
+    pub fn advance(&self, duration: Duration) {
+        self.now
+            .fetch_add(duration.as_millis() as u64, Ordering::SeqCst);
+    }
+
+    /// Move the clock backwards
+    pub fn rewind(&self, duration: Duration) {
+        self.now
+            .fetch_sub(duration.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_millis(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+
+    fn wait(&self, strategy: WaitStrategy) {
+        match strategy {
+            WaitStrategy::Sleep(duration) => self.sleep(duration),
+            _ => self.advance(Duration::from_millis(1)),
+        }
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration.max(Duration::from_millis(1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn system_clock_is_current() {
+        let now = Utc::now().timestamp_millis() as u64;
+        assert!(SystemClock.now_millis().abs_diff(now) < 1000);
+    }
+
+    #[test]
+    fn mock_clock_is_shared() {
+        let clock = MockClock::new(1000);
+        let handle = clock.clone();
+
+        handle.advance(Duration::from_millis(500));
+        assert_eq!(clock.now_millis(), 1500);
+
+        handle.rewind(Duration::from_secs(1));
+        assert_eq!(clock.now_millis(), 500);
+
+        clock.wait(WaitStrategy::Spin);
+        clock.sleep(Duration::from_millis(10));
+        assert_eq!(handle.now_millis(), 511);
+    }
+}
@@ -0,0 +1,259 @@
+use crate::Error;
+
+const CROCKFORD: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const BASE62: &[u8; 62] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const BASE58: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const DECIMAL: &[u8; 10] = b"0123456789";
+
+/// Text encodings for IDs
+///
+/// Every alphabet is in ASCII order and IDs are padded to a fixed width, so
+/// encoded strings sort in the same order as the IDs themselves.
+///
+/// ```rust
+/// use chronoflake::Encoding;
+///
+/// let id = 1704967240656416804;
+/// let encoded = Encoding::Crockford.encode(id);
+/// assert_eq!(encoded, "1FAA2NG6G1W14");
+/// assert_eq!(Encoding::Crockford.decode(&encoded).unwrap(), id);
+///
+/// assert!(Encoding::Base62.encode(1) < Encoding::Base62.encode(u64::MAX));
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Encoding {
+    /// Crockford's base32: 13 characters, case-insensitive, no `I`, `L`, `O` or `U`
+    Crockford,
+
+    /// Digits, upper and lower case letters: 11 characters
+    Base62,
+
+    /// Base62 without `0`, `O`, `I` and `l`: 11 characters
+    Base58,
+
+    /// Zero-padded decimal: 20 characters
+    Decimal,
+}
+
+impl Encoding {
+    /// Length of every encoded ID
+    pub fn width(&self) -> usize {
+        match self {
+            Self::Crockford => 13,
+            Self::Base62 | Self::Base58 => 11,
+            Self::Decimal => 20,
+        }
+    }
+
+    /// Encode an ID as a fixed-width string
+    pub fn encode(&self, mut id: u64) -> String {
+        let alphabet = self.alphabet();
+        let base = alphabet.len() as u64;
+
+        let mut encoded = vec![alphabet[0]; self.width()];
+        for c in encoded.iter_mut().rev() {
+            *c = alphabet[(id % base) as usize];
+            id /= base;
+        }
+
+        String::from_utf8(encoded).expect("alphabets are ASCII")
+    }
+
+    /// Decode an encoded ID
+    ///
+    /// Leading padding may be left off. Crockford strings are case-insensitive
+    /// and accept `I` and `L` for `1` and `O` for `0`.
+    pub fn decode(&self, encoded: &str) -> Result<u64, Error> {
+        let length = encoded.chars().count();
+        if length == 0 || length > self.width() {
+            return Err(Error::InvalidLength {
+                length,
+                max: self.width(),
+            });
+        }
+
+        let base = self.alphabet().len() as u64;
+        encoded
+            .chars()
+            .enumerate()
+            .try_fold(0u64, |id, (position, character)| {
+                let digit = self.digit(character).ok_or(Error::InvalidCharacter {
+                    character,
+                    position,
+                })?;
+
+                id.checked_mul(base)
+                    .and_then(|id| id.checked_add(digit))
+                    .ok_or(Error::DecodeOverflow)
+            })
+    }
+
+    /// Encode a big-endian number of any size as a string of `width` characters
+    pub(crate) fn encode_bytes(&self, bytes: &[u8], width: usize) -> String {
+        let alphabet = self.alphabet();
+        let base = alphabet.len() as u32;
+
+        // Long division of the whole number by the base, once per character
+        let mut number = bytes.to_vec();
+        let mut encoded = vec![alphabet[0]; width];
+        for c in encoded.iter_mut().rev() {
+            let mut remainder = 0;
+            for byte in number.iter_mut() {
+                let value = remainder << 8 | *byte as u32;
+                *byte = (value / base) as u8;
+                remainder = value % base;
+            }
+            *c = alphabet[remainder as usize];
+        }
+
+        String::from_utf8(encoded).expect("alphabets are ASCII")
+    }
+
+    /// Decode a string of up to `width` characters into a big-endian number of `N` bytes
+    pub(crate) fn decode_bytes<const N: usize>(
+        &self,
+        encoded: &str,
+        width: usize,
+    ) -> Result<[u8; N], Error> {
+        let length = encoded.chars().count();
+        if length == 0 || length > width {
+            return Err(Error::InvalidLength { length, max: width });
+        }
+
+        let base = self.alphabet().len() as u32;
+        let mut bytes = [0; N];
+        for (position, character) in encoded.chars().enumerate() {
+            let mut carry = self.digit(character).ok_or(Error::InvalidCharacter {
+                character,
+                position,
+            })? as u32;
+
+            for byte in bytes.iter_mut().rev() {
+                let value = *byte as u32 * base + carry;
+                *byte = value as u8;
+                carry = value >> 8;
+            }
+
+            if carry != 0 {
+                return Err(Error::DecodeOverflow);
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn alphabet(&self) -> &'static [u8] {
+        match self {
+            Self::Crockford => CROCKFORD,
+            Self::Base62 => BASE62,
+            Self::Base58 => BASE58,
+            Self::Decimal => DECIMAL,
+        }
+    }
+
+    fn digit(&self, character: char) -> Option<u64> {
+        let character = match self {
+            Self::Crockford => match character.to_ascii_uppercase() {
+                'I' | 'L' => '1',
+                'O' => '0',
+                c => c,
+            },
+            _ => character,
+        };
+
+        if !character.is_ascii() {
+            return None;
+        }
+
+        self.alphabet()
+            .iter()
+            .position(|&c| c == character as u8)
+            .map(|digit| digit as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Encoding; 4] = [
+        Encoding::Crockford,
+        Encoding::Base62,
+        Encoding::Base58,
+        Encoding::Decimal,
+    ];
+
+    #[test]
+    fn round_trip_and_order() {
+        let ids = [0, 1, 57, 58, 61, 62, 1 << 32, 1704967240656416804, u64::MAX];
+        for encoding in ALL {
+            let encoded: Vec<String> = ids.iter().map(|&id| encoding.encode(id)).collect();
+            assert!(encoded.iter().all(|e| e.len() == encoding.width()));
+            assert!(encoded.windows(2).all(|w| w[0] < w[1]), "{encoding:?}");
+
+            for (id, encoded) in ids.iter().zip(&encoded) {
+                assert_eq!(encoding.decode(encoded).unwrap(), *id);
+            }
+        }
+    }
+
+    #[test]
+    fn known_values() {
+        assert_eq!(Encoding::Decimal.encode(42u64), "00000000000000000042");
+        assert_eq!(Encoding::Base62.encode(u64::MAX), "LygHa16AHYF");
+        assert_eq!(Encoding::Base58.encode(u64::MAX), "jpXCZedGfVQ");
+        assert_eq!(Encoding::Crockford.encode(u64::MAX), "FZZZZZZZZZZZZ");
+    }
+
+    #[test]
+    fn crockford_is_forgiving() {
+        let id = Encoding::Crockford.decode("1FAA2NG6G1W14").unwrap();
+        assert_eq!(Encoding::Crockford.decode("1faa2ng6g1w14").unwrap(), id);
+        assert_eq!(Encoding::Crockford.decode("o1"), Ok(1));
+        assert_eq!(Encoding::Crockford.decode("Li"), Ok(33));
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert_eq!(
+            Encoding::Crockford.decode("01U"),
+            Err(Error::InvalidCharacter {
+                character: 'U',
+                position: 2
+            })
+        );
+        assert_eq!(
+            Encoding::Base58.decode("0"),
+            Err(Error::InvalidCharacter {
+                character: '0',
+                position: 0
+            })
+        );
+        assert_eq!(
+            Encoding::Base62.decode("é"),
+            Err(Error::InvalidCharacter {
+                character: 'é',
+                position: 0
+            })
+        );
+        assert_eq!(
+            Encoding::Base62.decode(""),
+            Err(Error::InvalidLength { length: 0, max: 11 })
+        );
+        assert_eq!(
+            Encoding::Decimal.decode("000000000000000000001"),
+            Err(Error::InvalidLength {
+                length: 21,
+                max: 20
+            })
+        );
+        assert_eq!(
+            Encoding::Decimal.decode("18446744073709551616"),
+            Err(Error::DecodeOverflow)
+        );
+        assert_eq!(
+            Encoding::Crockford.decode("G000000000000"),
+            Err(Error::DecodeOverflow)
+        );
+    }
+}
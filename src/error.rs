@@ -0,0 +1,168 @@
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
+
+/// Errors that can occur when generating IDs
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// Every sequence number for the current timestamp has already been issued
+    SequenceExhausted,
+
+    /// The clock is behind the last issued ID by the given number of milliseconds
+    ClockMovedBackwards { by: u64 },
+
+    /// The clock reports a time earlier than the generator's epoch
+    ClockBeforeEpoch,
+
+    /// The bit layout is not valid
+    InvalidLayout(String),
+
+    /// No shard ID was given to the builder
+    MissingShardId,
+
+    /// The shard ID does not fit in the layout's shard field
+    ShardIdTooLarge { shard_id: u16, max: u16 },
+
+    /// The epoch is later than the current time
+    EpochInFuture { epoch: u64 },
+
+    /// The time since the epoch does not fit in the layout's timestamp field
+    TimestampOverflow { max: u64 },
+
+    /// The state file could not be read or written
+    StateFile { path: PathBuf, reason: String },
+
+    /// An encoded ID contains a character outside the encoding's alphabet
+    InvalidCharacter { character: char, position: usize },
+
+    /// An encoded ID is empty or longer than the encoding allows
+    InvalidLength { length: usize, max: usize },
+
+    /// An encoded ID is too large for the type it decodes to
+    DecodeOverflow,
+
+    /// No preset has the given name
+    UnknownPreset(String),
+
+    /// The shard ID could not be found from the given source
+    ShardUnavailable(String),
+
+    /// A shard lease lock file could not be opened or locked
+    ShardLease { path: PathBuf, reason: String },
+
+    /// Every shard ID in the layout is already leased, up to the given maximum
+    NoFreeShard { max: u16 },
+
+    /// Every shard ID in the layout outside the range reserved for backfilling is
+    /// already leased
+    NoUnreservedShard {
+        max: u16,
+        reserved: RangeInclusive<u16>,
+    },
+
+    /// An HTTP request failed, with the status code if the server responded
+    Http { status: Option<u16>, reason: String },
+
+    /// Every reserved shard has used up its sequence numbers for the timestamp
+    BackfillExhausted { timestamp: u64 },
+
+    /// The shard ID is reserved for backfilling and can't be used by a live generator
+    ShardReserved { shard_id: u16 },
+
+    /// A timestamp given to generate an ID for is earlier than the epoch
+    TimestampBeforeEpoch,
+
+    /// The range of shard IDs reserved for backfilling is empty
+    EmptyShardRange,
+
+    /// The sequence number does not fit in the layout's sequence field
+    SequenceTooLarge { sequence: u16, max: u16 },
+
+    /// The timestamp is before the epoch or falls between two of the layout's ticks
+    TimestampNotRepresentable { timestamp: u64 },
+
+    /// A UUID does not hold an ID embedded with `Chronoflake::to_uuid`
+    NotAnEmbeddedId,
+
+    /// An ID's timestamp is outside the range of dates that can be represented
+    DatetimeOutOfRange,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SequenceExhausted => {
+                write!(f, "sequence exhausted for the current timestamp")
+            }
+            Self::ClockMovedBackwards { by } => {
+                write!(f, "clock moved backwards by {by}ms")
+            }
+            Self::ClockBeforeEpoch => write!(f, "clock is earlier than the epoch"),
+            Self::InvalidLayout(reason) => write!(f, "invalid layout: {reason}"),
+            Self::MissingShardId => write!(f, "no shard ID was given"),
+            Self::ShardIdTooLarge { shard_id, max } => {
+                write!(f, "shard ID {shard_id} is larger than the maximum of {max}")
+            }
+            Self::EpochInFuture { epoch } => write!(f, "epoch {epoch} is in the future"),
+            Self::TimestampOverflow { max } => {
+                write!(f, "timestamp exceeds the maximum of {max} since the epoch")
+            }
+            Self::StateFile { path, reason } => {
+                write!(f, "state file {}: {reason}", path.display())
+            }
+            Self::InvalidCharacter {
+                character,
+                position,
+            } => write!(f, "invalid character {character:?} at position {position}"),
+            Self::InvalidLength { length, max } => {
+                write!(f, "encoded ID is {length} characters, expected 1 to {max}")
+            }
+            Self::DecodeOverflow => write!(f, "encoded ID is out of range"),
+            Self::UnknownPreset(name) => write!(f, "unknown preset {name:?}"),
+            Self::ShardUnavailable(reason) => write!(f, "shard ID unavailable: {reason}"),
+            Self::ShardLease { path, reason } => {
+                write!(f, "shard lease {}: {reason}", path.display())
+            }
+            Self::NoFreeShard { max } => {
+                write!(f, "every shard ID from 0 to {max} is already leased")
+            }
+            Self::NoUnreservedShard { max, reserved } => write!(
+                f,
+                "every shard ID from 0 to {max} outside the {}-{} reserved for backfilling is already leased",
+                reserved.start(),
+                reserved.end()
+            ),
+            Self::Http {
+                status: Some(status),
+                reason,
+            } => write!(f, "HTTP {status}: {reason}"),
+            Self::Http {
+                status: None,
+                reason,
+            } => write!(f, "HTTP request failed: {reason}"),
+            Self::BackfillExhausted { timestamp } => {
+                write!(f, "no backfill IDs left for timestamp {timestamp}")
+            }
+            Self::ShardReserved { shard_id } => {
+                write!(f, "shard ID {shard_id} is reserved for backfilling")
+            }
+            Self::TimestampBeforeEpoch => write!(f, "timestamp is earlier than the epoch"),
+            Self::EmptyShardRange => write!(f, "no shard IDs are reserved for backfilling"),
+            Self::SequenceTooLarge { sequence, max } => {
+                write!(f, "sequence {sequence} is larger than the maximum of {max}")
+            }
+            Self::TimestampNotRepresentable { timestamp } => {
+                write!(
+                    f,
+                    "timestamp {timestamp} can't be represented in the layout"
+                )
+            }
+            Self::NotAnEmbeddedId => write!(f, "UUID does not contain an embedded ID"),
+            Self::DatetimeOutOfRange => {
+                write!(f, "timestamp is outside the range of supported dates")
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,403 @@
+//! ID service over HTTP and a client for it
+//!
+//! [`Server`] shares an [`AtomicIdGenerator`] over HTTP so that programs which
+//! cannot use this crate directly can still get IDs from it, and [`Client`] talks
+//! to a running server. IDs are sent as decimal strings because JSON numbers lose
+//! precision above 2^53. The server answers:
+//!
+//! | Request              | Response                                                  |
+//! |----------------------|-----------------------------------------------------------|
+//! | `GET /id`            | `{"id":"…"}`                                              |
+//! | `GET /ids?count=N`   | `{"ids":["…","…"]}`, up to [`MAX_BATCH`] IDs               |
+//! | `GET /decode/{id}`   | `{"id":"…","timestamp":…,"datetime":"…","shard_id":…,"sequence":…}` |
+//!
+//! Failures are reported as `{"error":"…"}` with status 400 for bad input, 404
+//! for unknown paths, 503 when the clock has gone backwards or the sequence is
+//! exhausted (with a `Retry-After` header), and 500 for anything else.
+//!
+//! ```rust,no_run
+//! use chronoflake::http::{Client, Server};
+//! use chronoflake::AtomicIdGenerator;
+//!
+//! let server = Server::bind("127.0.0.1:8080", AtomicIdGenerator::new(16)).unwrap();
+//! std::thread::spawn(move || server.run());
+//!
+//! let client = Client::new("127.0.0.1:8080");
+//! let id = client.generate_id().unwrap();
+//! assert_eq!(client.decode(id).unwrap().shard_id, 16);
+//! ```
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use ::serde::de::DeserializeOwned;
+use ::serde::{Deserialize, Serialize};
+use chrono::SecondsFormat;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::{AtomicIdGenerator, Clock, Error, SystemClock};
+
+/// Largest number of IDs the server hands out in one request
+pub const MAX_BATCH: usize = 10_000;
+
+/// The parts of an ID, as returned by `GET /decode/{id}`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedId {
+    /// The raw ID
+    #[serde(with = "crate::serde::string")]
+    pub id: u64,
+
+    /// Unix timestamp (in milliseconds) at which the ID was generated
+    pub timestamp: u64,
+
+    /// The timestamp in RFC 3339 format
+    pub datetime: String,
+
+    /// Machine or Shard ID that generated the ID
+    pub shard_id: u16,
+
+    /// Sequence number of the ID within its timestamp
+    pub sequence: u16,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Id(#[serde(with = "crate::serde::string")] u64);
+
+#[derive(Serialize, Deserialize)]
+struct IdBody {
+    id: Id,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IdsBody {
+    ids: Vec<Id>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ErrorBody {
+    error: String,
+}
+
+/// HTTP server handing out IDs from a generator
+pub struct Server<C = SystemClock> {
+    http: tiny_http::Server,
+    generator: AtomicIdGenerator<C>,
+}
+
+impl<C: Clock> Server<C> {
+    /// Listen on the given address, e.g. `127.0.0.1:8080`
+    ///
+    /// Use port 0 to pick a free port, which [`local_addr`](Self::local_addr) reports.
+    pub fn bind(addr: impl ToSocketAddrs, generator: AtomicIdGenerator<C>) -> Result<Self, Error> {
+        let http = tiny_http::Server::http(addr).map_err(|e| Error::Http {
+            status: None,
+            reason: e.to_string(),
+        })?;
+
+        Ok(Self { http, generator })
+    }
+
+    /// The generator handing out IDs
+    pub fn generator(&self) -> &AtomicIdGenerator<C> {
+        &self.generator
+    }
+
+    /// Address the server is listening on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.http
+            .server_addr()
+            .to_ip()
+            .expect("server listens on TCP")
+    }
+
+    /// Handle requests one at a time until [`unblock`](Self::unblock) is called
+    pub fn run(&self) {
+        for request in self.http.incoming_requests() {
+            self.handle(request);
+        }
+    }
+
+    /// Make [`run`](Self::run) return, e.g. from another thread when shutting down
+    pub fn unblock(&self) {
+        self.http.unblock();
+    }
+
+    fn handle(&self, request: Request) {
+        let (status, body) = match request.method() {
+            Method::Get => self.route(request.url()),
+            _ => error(405, "only GET is supported"),
+        };
+
+        let mut response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header("Content-Type", "application/json"));
+        if status == 503 {
+            response.add_header(header("Retry-After", "1"));
+        }
+
+        // The client may have gone away, which is no concern of the server
+        let _ = request.respond(response);
+    }
+
+    /// Status code and JSON body for a GET of the given URL
+    fn route(&self, url: &str) -> (u16, String) {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let result = match path.trim_end_matches('/') {
+            "/id" => self
+                .generator
+                .generate_id()
+                .map(|id| json(&IdBody { id: Id(id) })),
+            "/ids" => {
+                let count = query
+                    .split('&')
+                    .find_map(|pair| pair.strip_prefix("count="))
+                    .and_then(|count| count.parse().ok())
+                    .filter(|count| (1..=MAX_BATCH).contains(count));
+                let Some(count) = count else {
+                    return error(400, &format!("count must be from 1 to {MAX_BATCH}"));
+                };
+
+                self.generator.generate_batch(count).map(|ids| {
+                    json(&IdsBody {
+                        ids: ids.into_iter().map(Id).collect(),
+                    })
+                })
+            }
+            path => match path.strip_prefix("/decode/") {
+                Some(id) => return self.decode(id),
+                None => return error(404, &format!("no such endpoint {path:?}")),
+            },
+        };
+
+        match result {
+            Ok(body) => (200, body),
+            Err(err @ (Error::SequenceExhausted | Error::ClockMovedBackwards { .. })) => {
+                error(503, &err.to_string())
+            }
+            Err(err) => error(500, &err.to_string()),
+        }
+    }
+
+    fn decode(&self, id: &str) -> (u16, String) {
+        let Ok(raw) = id.parse::<u64>() else {
+            return error(400, &format!("{id:?} is not a valid ID"));
+        };
+
+        let layout = self.generator.layout();
+        let id = self.generator.decode(raw);
+        let datetime = match id.try_datetime() {
+            Ok(datetime) => datetime,
+            Err(Error::TimestampOverflow { .. }) => {
+                return error(400, &format!("{raw} does not fit the layout {layout}"));
+            }
+            Err(err) => return error(400, &format!("{raw}: {err}")),
+        };
+
+        let decoded = DecodedId {
+            id: raw,
+            timestamp: id.timestamp(),
+            datetime: datetime.to_rfc3339_opts(SecondsFormat::Millis, true),
+            shard_id: id.shard_id(),
+            sequence: id.sequence(),
+        };
+        (200, json(&decoded))
+    }
+}
+
+fn json(body: &impl Serialize) -> String {
+    serde_json::to_string(body).expect("response bodies serialize to JSON")
+}
+
+fn error(status: u16, reason: &str) -> (u16, String) {
+    let body = ErrorBody {
+        error: reason.to_string(),
+    };
+    (status, json(&body))
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field, value).expect("header is valid ASCII")
+}
+
+/// Client for an ID [`Server`]
+///
+/// Each call makes a new connection, so a client can be shared freely.
+#[derive(Clone, Debug)]
+pub struct Client {
+    addr: String,
+    timeout: Duration,
+}
+
+impl Client {
+    /// Talk to the server at the given `host:port`
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Set how long to wait for the server to connect and respond
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Get a unique ID from the server
+    pub fn generate_id(&self) -> Result<u64, Error> {
+        let IdBody { id: Id(id) } = self.get("/id")?;
+        Ok(id)
+    }
+
+    /// Get `count` unique IDs from the server, at most [`MAX_BATCH`]
+    pub fn generate_batch(&self, count: usize) -> Result<Vec<u64>, Error> {
+        let IdsBody { ids } = self.get(&format!("/ids?count={count}"))?;
+        Ok(ids.into_iter().map(|Id(id)| id).collect())
+    }
+
+    /// Have the server decode an ID with its epoch and layout
+    pub fn decode(&self, id: u64) -> Result<DecodedId, Error> {
+        self.get(&format!("/decode/{id}"))
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, Error> {
+        let (status, body) = self.request(path).map_err(|e| Error::Http {
+            status: None,
+            reason: e.to_string(),
+        })?;
+
+        if status != 200 {
+            let reason = serde_json::from_slice::<ErrorBody>(&body)
+                .map(|body| body.error)
+                .unwrap_or_else(|_| String::from_utf8_lossy(&body).into_owned());
+            return Err(Error::Http {
+                status: Some(status),
+                reason,
+            });
+        }
+
+        serde_json::from_slice(&body).map_err(|e| Error::Http {
+            status: Some(status),
+            reason: format!("invalid response: {e}"),
+        })
+    }
+
+    /// Send a GET and read back the status code and body
+    fn request(&self, path: &str) -> std::io::Result<(u16, Vec<u8>)> {
+        let invalid = |reason: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, reason);
+
+        let addr = self
+            .addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| invalid("address did not resolve"))?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout)?;
+        stream.set_read_timeout(Some(self.timeout))?;
+        stream.set_write_timeout(Some(self.timeout))?;
+
+        // HTTP/1.0 keeps the response unchunked and closes the connection after it
+        write!(stream, "GET {path} HTTP/1.0\r\nHost: {}\r\n\r\n", self.addr)?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+
+        let end = response
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .ok_or_else(|| invalid("response has no body"))?;
+        let head = String::from_utf8_lossy(&response[..end]);
+        let status = head
+            .split_whitespace()
+            .nth(1)
+            .and_then(|status| status.parse().ok())
+            .ok_or_else(|| invalid("response has no status"))?;
+
+        Ok((status, response[end + 4..].to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use crate::{IdGenerator, MockClock, RollbackPolicy, DEFAULT_EPOCH};
+
+    fn start(generator: AtomicIdGenerator<MockClock>) -> (Arc<Server<MockClock>>, Client) {
+        let server = Arc::new(Server::bind("127.0.0.1:0", generator).unwrap());
+        let client = Client::new(server.local_addr().to_string());
+
+        let handle = Arc::clone(&server);
+        std::thread::spawn(move || handle.run());
+        (server, client)
+    }
+
+    #[test]
+    fn serves_ids_end_to_end() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let (server, client) = start(IdGenerator::new(49).with_clock(clock.clone()).into());
+
+        let first = client.generate_id().unwrap();
+        let batch = client.generate_batch(MAX_BATCH).unwrap();
+        assert_eq!(batch.len(), MAX_BATCH);
+        assert!(first < batch[0]);
+        assert!(batch.windows(2).all(|w| w[0] < w[1]));
+
+        let decoded = client.decode(first).unwrap();
+        assert_eq!(
+            decoded,
+            DecodedId {
+                id: first,
+                timestamp: DEFAULT_EPOCH + 1000,
+                datetime: "2010-11-04T01:42:55.657Z".to_string(),
+                shard_id: 49,
+                sequence: 0,
+            }
+        );
+
+        let ids: HashSet<u64> = (0..100).map(|_| client.generate_id().unwrap()).collect();
+        assert_eq!(ids.len(), 100);
+
+        server.unblock();
+    }
+
+    #[test]
+    fn reports_errors_as_status_codes() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let generator = IdGenerator::new(49)
+            .with_clock(clock.clone())
+            .with_rollback_policy(RollbackPolicy::Error);
+        let (server, client) = start(generator.into());
+
+        fn status<T>(result: Result<T, Error>) -> Option<u16> {
+            match result {
+                Err(Error::Http { status, .. }) => status,
+                _ => None,
+            }
+        }
+
+        client.generate_id().unwrap();
+        clock.rewind(Duration::from_millis(10));
+        let err = client.generate_id().unwrap_err();
+        assert_eq!(
+            err,
+            Error::Http {
+                status: Some(503),
+                reason: "clock moved backwards by 10ms".to_string()
+            }
+        );
+
+        assert_eq!(status(client.generate_batch(0)), Some(400));
+        assert_eq!(status(client.generate_batch(MAX_BATCH + 1)), Some(400));
+        assert_eq!(status(client.get::<DecodedId>("/decode/abc")), Some(400));
+        assert_eq!(status(client.decode(u64::MAX)), Some(400));
+        assert_eq!(status(client.get::<IdBody>("/uuid")), Some(404));
+
+        server.unblock();
+        let unreachable = Client::new("127.0.0.1:1").with_timeout(Duration::from_millis(100));
+        assert!(matches!(
+            unreachable.generate_id(),
+            Err(Error::Http { status: None, .. })
+        ));
+    }
+}
@@ -0,0 +1,337 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::{Error, Layout, Preset, DEFAULT_EPOCH};
+
+/// A generated ID along with the epoch and layout needed to decode it
+///
+/// ```rust
+/// use chronoflake::Chronoflake;
+///
+/// let id = Chronoflake::new(1704967240656416804).with_epoch(1488432924251);
+/// println!("Created at {} on shard {}", id.datetime(), id.shard_id());
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Chronoflake {
+    id: u64,
+    epoch: u64,
+    layout: Layout,
+}
+
+impl Chronoflake {
+    /// Wrap a raw ID that was generated with the default epoch and layout
+    pub fn new(id: u64) -> Self {
+        Self {
+            id,
+            epoch: DEFAULT_EPOCH,
+            layout: Layout::DEFAULT,
+        }
+    }
+
+    /// Set the epoch (in milliseconds) the ID was generated with
+    pub fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Set the bit layout the ID was generated with
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set the epoch and layout from a well-known ID scheme
+    pub fn with_preset(self, preset: Preset) -> Self {
+        self.with_epoch(preset.epoch).with_layout(preset.layout)
+    }
+
+    /// The raw ID
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// The epoch (in milliseconds) the ID is relative to
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The bit layout of the ID
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Unix timestamp (in milliseconds) at which the ID was generated
+    ///
+    /// # Panics
+    ///
+    /// Panics if the epoch is so large that the timestamp overflows a `u64`
+    pub fn timestamp(&self) -> u64 {
+        self.checked_timestamp().expect("timestamp out of range")
+    }
+
+    /// Time at which the ID was generated
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timestamp is outside the range supported by `chrono`, see
+    /// [`try_datetime`](Self::try_datetime)
+    pub fn datetime(&self) -> DateTime<Utc> {
+        self.try_datetime().expect("timestamp out of range")
+    }
+
+    /// Time at which the ID was generated, checking that the ID fits its layout
+    ///
+    /// Fails with [`Error::TimestampOverflow`] if the ID has bits set above the
+    /// timestamp field, and with [`Error::DatetimeOutOfRange`] if the epoch puts
+    /// the timestamp outside the range supported by `chrono`.
+    ///
+    /// ```rust
+    /// use chronoflake::Chronoflake;
+    ///
+    /// let id = Chronoflake::new(1704967240656416804);
+    /// assert!(id.try_datetime().is_ok());
+    /// assert!(id.with_epoch(9000000000000000).try_datetime().is_err());
+    /// ```
+    pub fn try_datetime(&self) -> Result<DateTime<Utc>, Error> {
+        let max = self.layout.max_timestamp();
+        if self.id >> (self.layout.shard_bits() + self.layout.sequence_bits()) > max {
+            return Err(Error::TimestampOverflow { max });
+        }
+
+        self.checked_timestamp()
+            .and_then(|timestamp| i64::try_from(timestamp).ok())
+            .and_then(|timestamp| Utc.timestamp_millis_opt(timestamp).single())
+            .ok_or(Error::DatetimeOutOfRange)
+    }
+
+    fn checked_timestamp(&self) -> Option<u64> {
+        self.epoch
+            .checked_add(self.layout.to_millis(self.layout.timestamp(self.id)))
+    }
+
+    /// Machine or Shard ID that generated the ID
+    pub fn shard_id(&self) -> u16 {
+        self.layout.shard_id(self.id)
+    }
+
+    /// Sequence number of the ID within its timestamp
+    pub fn sequence(&self) -> u16 {
+        self.layout.sequence(self.id)
+    }
+
+    /// Re-encode the ID with another epoch and layout, keeping its timestamp,
+    /// shard ID and sequence
+    ///
+    /// Fails if a field does not fit the target: [`Error::ShardIdTooLarge`] or
+    /// [`Error::SequenceTooLarge`] if the target fields are too narrow,
+    /// [`Error::TimestampOverflow`] if the timestamp is past the end of the target
+    /// layout and [`Error::TimestampNotRepresentable`] if it is before the target
+    /// epoch or does not fall on the start of one of the target's ticks.
+    ///
+    /// ```rust
+    /// use chronoflake::{Chronoflake, Layout};
+    ///
+    /// let layout = Layout::new(39, 14, 10).unwrap();
+    /// let id = Chronoflake::new(1704967240656416804);
+    /// let converted = id.convert(1488432924251, layout).unwrap();
+    /// assert_eq!(converted.timestamp(), id.timestamp());
+    /// assert_eq!(converted.shard_id(), id.shard_id());
+    /// ```
+    pub fn convert(&self, epoch: u64, layout: Layout) -> Result<Self, Error> {
+        let (shard_id, sequence) = (self.shard_id(), self.sequence());
+        let max = layout.max_shard_id();
+        if shard_id > max {
+            return Err(Error::ShardIdTooLarge { shard_id, max });
+        }
+        let max = layout.max_sequence();
+        if sequence > max {
+            return Err(Error::SequenceTooLarge { sequence, max });
+        }
+
+        let timestamp = self.timestamp();
+        let since_epoch = timestamp
+            .checked_sub(epoch)
+            .filter(|since_epoch| since_epoch.is_multiple_of(layout.tick_millis()))
+            .ok_or(Error::TimestampNotRepresentable { timestamp })?;
+        let ticks = layout.to_ticks(since_epoch);
+        if ticks > layout.max_timestamp() {
+            return Err(Error::TimestampOverflow {
+                max: layout.max_timestamp(),
+            });
+        }
+
+        Ok(Self {
+            id: layout.compose(ticks, shard_id, sequence),
+            epoch,
+            layout,
+        })
+    }
+
+    /// Re-encode the ID with the epoch and layout of a well-known ID scheme
+    ///
+    /// See [`convert`](Self::convert).
+    pub fn convert_to(&self, preset: Preset) -> Result<Self, Error> {
+        self.convert(preset.epoch, preset.layout)
+    }
+}
+
+impl From<u64> for Chronoflake {
+    fn from(id: u64) -> Self {
+        Self::new(id)
+    }
+}
+
+impl From<Chronoflake> for u64 {
+    fn from(id: Chronoflake) -> Self {
+        id.id
+    }
+}
+
+/// Serialized as the raw ID; the epoch and layout are not included
+#[cfg(feature = "serde")]
+impl serde::Serialize for Chronoflake {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.id)
+    }
+}
+
+/// Deserialized from a number or a decimal string, with the default epoch and layout
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Chronoflake {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        crate::serde::string::deserialize(deserializer)
+    }
+}
+
+impl fmt::Display for Chronoflake {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)
+    }
+}
+
+impl FromStr for Chronoflake {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(Self::new)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IdGenerator;
+    use std::time::Duration;
+
+    #[test]
+    fn decode_parts() {
+        let id = Chronoflake::new((1234 << 22) | (49 << 12) | 7).with_epoch(1000);
+        assert_eq!(id.timestamp(), 2234);
+        assert_eq!(id.datetime().timestamp_millis(), 2234);
+        assert_eq!(id.shard_id(), 49);
+        assert_eq!(id.sequence(), 7);
+
+        let layout = Layout::new(39, 14, 10).unwrap();
+        let id = Chronoflake::new((1234 << 24) | (9000 << 10) | 7).with_layout(layout);
+        assert_eq!(id.timestamp(), DEFAULT_EPOCH + 1234);
+        assert_eq!(id.shard_id(), 9000);
+        assert_eq!(id.sequence(), 7);
+    }
+
+    #[test]
+    fn datetime_out_of_range() {
+        let id = Chronoflake::new(1704967240656416804);
+        assert_eq!(
+            id.try_datetime().unwrap().to_rfc3339(),
+            "2023-09-21T21:14:01.589+00:00"
+        );
+
+        assert_eq!(
+            id.with_epoch(9000000000000000).try_datetime(),
+            Err(Error::DatetimeOutOfRange)
+        );
+        assert_eq!(
+            id.with_epoch(u64::MAX).try_datetime(),
+            Err(Error::DatetimeOutOfRange)
+        );
+        assert_eq!(
+            Chronoflake::new(u64::MAX).try_datetime(),
+            Err(Error::TimestampOverflow { max: (1 << 41) - 1 })
+        );
+    }
+
+    #[test]
+    fn decode_generated() {
+        let mut cf = IdGenerator::new(49).with_epoch(1488432924251);
+        let raw = cf.generate_id().unwrap();
+        let id = cf.decode(raw);
+
+        assert_eq!(id.timestamp(), cf.timestamp);
+        assert_eq!(id.shard_id(), 49);
+        assert_eq!(id.sequence(), cf.sequence);
+    }
+
+    #[test]
+    fn convert_between_layouts() {
+        let target = Layout::new(39, 14, 10).unwrap();
+        let id = Chronoflake::new((1234 << 22) | (49 << 12) | 7).with_epoch(1000);
+
+        let converted = id.convert(2000, target).unwrap();
+        assert_eq!(converted.id(), (234 << 24) | (49 << 10) | 7);
+        assert_eq!(converted.timestamp(), id.timestamp());
+        assert_eq!(converted.convert(1000, Layout::DEFAULT), Ok(id));
+
+        let id = Chronoflake::new((1234 << 22) | (49 << 12) | 1024);
+        assert_eq!(
+            id.convert(DEFAULT_EPOCH, target),
+            Err(Error::SequenceTooLarge {
+                sequence: 1024,
+                max: 1023
+            })
+        );
+        let id = Chronoflake::new((1234 << 24) | (9000 << 10) | 7).with_layout(target);
+        assert_eq!(
+            id.convert(DEFAULT_EPOCH, Layout::DEFAULT),
+            Err(Error::ShardIdTooLarge {
+                shard_id: 9000,
+                max: 1023
+            })
+        );
+    }
+
+    #[test]
+    fn convert_checks_timestamp() {
+        let id = Chronoflake::new(1234 << 22).with_epoch(1000);
+        assert_eq!(
+            id.convert(2235, Layout::DEFAULT),
+            Err(Error::TimestampNotRepresentable { timestamp: 2234 })
+        );
+
+        let ten_ms = Layout::DEFAULT
+            .with_tick(Duration::from_millis(10))
+            .unwrap();
+        assert_eq!(
+            id.convert(1000, ten_ms),
+            Err(Error::TimestampNotRepresentable { timestamp: 2234 })
+        );
+        assert_eq!(id.convert(1004, ten_ms).unwrap().timestamp(), 2234);
+
+        let narrow = Layout::new(31, 16, 16).unwrap();
+        let id = Chronoflake::new(1 << 62);
+        assert_eq!(
+            id.convert(DEFAULT_EPOCH, narrow),
+            Err(Error::TimestampOverflow { max: (1 << 31) - 1 })
+        );
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let id: Chronoflake = "1704967240656416804".parse().unwrap();
+        assert_eq!(u64::from(id), 1704967240656416804);
+        assert_eq!(id.to_string(), "1704967240656416804");
+        assert!("not-an-id".parse::<Chronoflake>().is_err());
+        assert!(Chronoflake::from(1) < Chronoflake::from(2));
+    }
+}
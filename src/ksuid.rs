@@ -0,0 +1,305 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::layout::FieldOrder;
+use crate::random::Random;
+use crate::{state, Clock, Encoding, Error, Layout, RollbackPolicy, SystemClock, WaitStrategy};
+
+/// Start of KSUID time, 2014-05-13T16:53:20Z, as a Unix timestamp (in milliseconds)
+pub const KSUID_EPOCH: u64 = 1_400_000_000_000;
+
+/// 32 bits of seconds since the KSUID epoch
+const KSUID_LAYOUT: Layout = Layout::from_parts(32, 0, 0, FieldOrder::ShardSequence, 1000);
+const PAYLOAD_BITS: u32 = 128;
+const WIDTH: usize = 27;
+
+/// K-Sortable Unique Identifier, as used by Segment
+///
+/// A KSUID is 20 bytes: a 32-bit count of seconds since [`KSUID_EPOCH`] followed
+/// by a 128-bit random payload, written as 27 base62 characters. Both the bytes
+/// and the strings sort by time.
+///
+/// ```rust
+/// use chronoflake::Ksuid;
+///
+/// let ksuid: Ksuid = "0ujtsYcgvSTl8PAuAdqWYSMnLOv".parse().unwrap();
+/// assert_eq!(ksuid.timestamp(), 1507608047000);
+/// assert_eq!(ksuid.to_string(), "0ujtsYcgvSTl8PAuAdqWYSMnLOv");
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Ksuid([u8; 20]);
+
+impl Ksuid {
+    /// Assemble a KSUID from seconds since [`KSUID_EPOCH`] and a payload
+    pub fn from_parts(seconds: u32, payload: u128) -> Self {
+        let mut bytes = [0; 20];
+        bytes[..4].copy_from_slice(&seconds.to_be_bytes());
+        bytes[4..].copy_from_slice(&payload.to_be_bytes());
+        Self(bytes)
+    }
+
+    /// Seconds since [`KSUID_EPOCH`] at which the KSUID was generated
+    pub fn seconds(&self) -> u32 {
+        u32::from_be_bytes(self.0[..4].try_into().expect("4 byte timestamp"))
+    }
+
+    /// Unix timestamp (in milliseconds) at which the KSUID was generated
+    ///
+    /// KSUIDs only record whole seconds.
+    pub fn timestamp(&self) -> u64 {
+        KSUID_EPOCH + self.seconds() as u64 * 1000
+    }
+
+    /// Time at which the KSUID was generated
+    pub fn datetime(&self) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(self.timestamp() as i64)
+            .single()
+            .expect("32-bit timestamps are in range")
+    }
+
+    /// The random payload of the KSUID
+    pub fn payload(&self) -> u128 {
+        u128::from_be_bytes(self.0[4..].try_into().expect("16 byte payload"))
+    }
+
+    /// The KSUID's 20 bytes
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+}
+
+impl From<[u8; 20]> for Ksuid {
+    fn from(bytes: [u8; 20]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl From<Ksuid> for [u8; 20] {
+    fn from(ksuid: Ksuid) -> Self {
+        ksuid.0
+    }
+}
+
+impl fmt::Display for Ksuid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Encoding::Base62.encode_bytes(&self.0, WIDTH))
+    }
+}
+
+impl FromStr for Ksuid {
+    type Err = Error;
+
+    /// Parse a KSUID from base62
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Encoding::Base62.decode_bytes(s, WIDTH).map(Self)
+    }
+}
+
+/// KSUID generator sharing the clock handling of [`IdGenerator`](crate::IdGenerator)
+///
+/// KSUIDs generated within the same second increment the payload of the last one
+/// rather than drawing a new one, so a generator's KSUIDs are strictly increasing.
+/// The clock going backwards is handled with the [`RollbackPolicy`], as for
+/// snowflake IDs.
+///
+/// The payload comes from the standard library's hasher seeds and is not suitable
+/// where KSUIDs must be unguessable.
+///
+/// ```rust
+/// use chronoflake::KsuidGenerator;
+///
+/// let mut generator = KsuidGenerator::new();
+/// let first = generator.generate_id().unwrap();
+/// let second = generator.generate_id().unwrap();
+/// assert!(first < second);
+/// assert!(first.to_string() < second.to_string());
+/// ```
+#[derive(Debug)]
+pub struct KsuidGenerator<C = SystemClock> {
+    /// Unix timestamp (in milliseconds) of the second of the last issued KSUID
+    pub timestamp: u64,
+
+    /// How to wait for the next second when the payload can't be incremented
+    pub wait_strategy: WaitStrategy,
+
+    /// How to react when the clock goes backwards
+    pub rollback_policy: RollbackPolicy,
+
+    /// Source of the current time
+    pub clock: C,
+
+    /// Payload of the last issued KSUID
+    payload: u128,
+    rng: Random,
+}
+
+impl KsuidGenerator {
+    /// Create a new KSUID generator
+    pub fn new() -> Self {
+        Self {
+            timestamp: 0,
+            wait_strategy: WaitStrategy::default(),
+            rollback_policy: RollbackPolicy::default(),
+            clock: SystemClock,
+            payload: 0,
+            rng: Random::new(),
+        }
+    }
+}
+
+impl Default for KsuidGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clone> Clone for KsuidGenerator<C> {
+    /// Copy the generator with a new random stream, so the copies issue different KSUIDs
+    ///
+    /// The copy continues from a fresh payload rather than the last KSUID's,
+    /// which would otherwise be incremented to the same value by both.
+    fn clone(&self) -> Self {
+        let mut rng = self.rng.clone();
+        Self {
+            timestamp: self.timestamp,
+            wait_strategy: self.wait_strategy,
+            rollback_policy: self.rollback_policy,
+            clock: self.clock.clone(),
+            payload: rng.next_u128(),
+            rng,
+        }
+    }
+}
+
+impl<C: Clock> KsuidGenerator<C> {
+    /// Use a different source of time for the generator
+    pub fn with_clock<D: Clock>(self, clock: D) -> KsuidGenerator<D> {
+        KsuidGenerator {
+            timestamp: 0,
+            wait_strategy: self.wait_strategy,
+            rollback_policy: self.rollback_policy,
+            clock,
+            payload: 0,
+            rng: self.rng,
+        }
+    }
+
+    /// Set how the generator waits when the payload can't be incremented
+    pub fn with_wait_strategy(mut self, wait_strategy: WaitStrategy) -> Self {
+        self.wait_strategy = wait_strategy;
+        self
+    }
+
+    /// Set how the generator reacts when the clock goes backwards
+    pub fn with_rollback_policy(mut self, rollback_policy: RollbackPolicy) -> Self {
+        self.rollback_policy = rollback_policy;
+        self
+    }
+
+    /// Generate a new KSUID
+    ///
+    /// Waits and fails in the same way as [`IdGenerator::generate_id`](crate::IdGenerator::generate_id).
+    pub fn generate_id(&mut self) -> Result<Ksuid, Error> {
+        loop {
+            match self.try_generate_id() {
+                Ok(ksuid) => return Ok(ksuid),
+                Err(err) => state::recover(
+                    &self.clock,
+                    &KSUID_LAYOUT,
+                    KSUID_EPOCH,
+                    self.wait_strategy,
+                    self.rollback_policy,
+                    err,
+                )?,
+            }
+        }
+    }
+
+    /// Generate a new KSUID without blocking
+    ///
+    /// Fails in the same way as [`IdGenerator::try_generate_id`](crate::IdGenerator::try_generate_id).
+    pub fn try_generate_id(&mut self) -> Result<Ksuid, Error> {
+        let now = self.clock.now_millis();
+        (self.timestamp, self.payload) = state::advance_random(
+            &KSUID_LAYOUT,
+            self.rollback_policy,
+            KSUID_EPOCH,
+            (self.timestamp, self.payload),
+            now,
+            self.rng.next_u128(),
+            PAYLOAD_BITS,
+        )?;
+
+        let seconds = KSUID_LAYOUT.to_ticks(self.timestamp - KSUID_EPOCH) as u32;
+        Ok(Ksuid::from_parts(seconds, self.payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockClock;
+    use std::time::Duration;
+
+    #[test]
+    fn reference_vectors() {
+        // From the reference Go implementation's README
+        let ksuid: Ksuid = "0ujtsYcgvSTl8PAuAdqWYSMnLOv".parse().unwrap();
+        assert_eq!(ksuid.seconds(), 107608047);
+        assert_eq!(ksuid.payload(), 0xB5A1CD34B5F99D1154FB6853345C9735);
+        assert_eq!(
+            Ksuid::from_parts(107608047, 0xB5A1CD34B5F99D1154FB6853345C9735).to_string(),
+            "0ujtsYcgvSTl8PAuAdqWYSMnLOv"
+        );
+        assert_eq!(ksuid.datetime().to_rfc3339(), "2017-10-10T04:00:47+00:00");
+
+        let max = Ksuid::from([0xFF; 20]);
+        assert_eq!(max.to_string(), "aWgEPTl1tmebfsQzFP4bxwgy80V");
+        assert_eq!(Ksuid::from([0; 20]).to_string(), "0".repeat(27));
+        assert_eq!(
+            "aWgEPTl1tmebfsQzFP4bxwgy80W".parse::<Ksuid>(),
+            Err(Error::DecodeOverflow)
+        );
+    }
+
+    #[test]
+    fn monotonic_within_second() {
+        let clock = MockClock::new(KSUID_EPOCH + 107_608_047_500);
+        let mut generator = KsuidGenerator::new().with_clock(clock.clone());
+
+        let first = generator.generate_id().unwrap();
+        assert_eq!(first.seconds(), 107608047);
+
+        clock.advance(Duration::from_millis(400));
+        let second = generator.generate_id().unwrap();
+        assert_eq!(second.seconds(), 107608047);
+        assert_eq!(second.payload(), first.payload().wrapping_add(1));
+
+        clock.advance(Duration::from_millis(100));
+        let third = generator.generate_id().unwrap();
+        assert_eq!(third.seconds(), 107608048);
+        assert!(first < second && second < third);
+        assert!(second.to_string() < third.to_string());
+    }
+
+    #[test]
+    fn clones_issue_different_ksuids() {
+        let clock = MockClock::new(KSUID_EPOCH + 107_608_047_500);
+        let mut generator = KsuidGenerator::new().with_clock(clock);
+        let mut clone = generator.clone();
+        assert_ne!(generator.generate_id(), clone.generate_id());
+
+        // Including within a second both have already issued KSUIDs in
+        let mut clone = generator.clone();
+        assert_ne!(generator.generate_id(), clone.generate_id());
+        assert_ne!(generator.generate_id(), clone.generate_id());
+    }
+
+    #[test]
+    fn clock_before_epoch() {
+        let mut generator = KsuidGenerator::new().with_clock(MockClock::new(KSUID_EPOCH - 1));
+        assert_eq!(generator.generate_id(), Err(Error::ClockBeforeEpoch));
+    }
+}
@@ -0,0 +1,479 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::Error;
+
+/// Total number of bits available to an ID, leaving the sign bit clear
+pub const ID_BITS: u8 = 63;
+
+/// Order of the shard and sequence fields below the timestamp
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldOrder {
+    /// `timestamp | shard | sequence`, as used by Twitter
+    #[default]
+    ShardSequence,
+
+    /// `timestamp | sequence | shard`, as used by Sonyflake
+    SequenceShard,
+}
+
+/// Bit widths of the timestamp, shard and sequence fields of an ID
+///
+/// The timestamp is always the most significant field, followed by the shard
+/// and sequence in the layout's [`FieldOrder`]. The widths must add up to
+/// [`ID_BITS`] so IDs stay positive when stored in signed 64-bit columns.
+///
+/// The timestamp counts ticks of 1ms by default. A longer [tick](Self::with_tick)
+/// stretches the lifetime of the timestamp field, so bits can be moved to the
+/// shard or sequence instead: 41 bits of milliseconds last about 69 years, while
+/// 32 bits of seconds last about 136.
+///
+/// ```rust
+/// use chronoflake::{IdGenerator, Layout};
+///
+/// // 39 bits of timestamp, 14 bits of shard, 10 bits of sequence
+/// let layout = Layout::new(39, 14, 10).unwrap();
+/// let mut cf = IdGenerator::new(9000).with_layout(layout);
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "LayoutFields")
+)]
+pub struct Layout {
+    timestamp_bits: u8,
+    shard_bits: u8,
+    sequence_bits: u8,
+    #[cfg_attr(feature = "serde", serde(default))]
+    order: FieldOrder,
+    #[cfg_attr(feature = "serde", serde(default = "default_tick"))]
+    tick_millis: u64,
+}
+
+impl Layout {
+    /// The Twitter Snowflake layout: 41 bits of timestamp, 10 of shard and 12 of sequence
+    pub const DEFAULT: Self = Self::from_parts(41, 10, 12, FieldOrder::ShardSequence, 1);
+
+    /// Create a layout without checking it, for presets known to be valid
+    pub(crate) const fn from_parts(
+        timestamp_bits: u8,
+        shard_bits: u8,
+        sequence_bits: u8,
+        order: FieldOrder,
+        tick_millis: u64,
+    ) -> Self {
+        Self {
+            timestamp_bits,
+            shard_bits,
+            sequence_bits,
+            order,
+            tick_millis,
+        }
+    }
+
+    /// Create a new layout from the width of each field
+    ///
+    /// The widths must add up to 63 and the shard and sequence fields can be at
+    /// most 16 bits wide.
+    pub fn new(timestamp_bits: u8, shard_bits: u8, sequence_bits: u8) -> Result<Self, Error> {
+        let total = timestamp_bits as u16 + shard_bits as u16 + sequence_bits as u16;
+        if total != ID_BITS as u16 {
+            return Err(Error::InvalidLayout(format!(
+                "fields add up to {total} bits instead of {ID_BITS}"
+            )));
+        }
+
+        if timestamp_bits == 0 {
+            return Err(Error::InvalidLayout(
+                "timestamp must be at least 1 bit".to_string(),
+            ));
+        }
+
+        if shard_bits > 16 || sequence_bits > 16 {
+            return Err(Error::InvalidLayout(
+                "shard and sequence can be at most 16 bits".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            timestamp_bits,
+            shard_bits,
+            sequence_bits,
+            order: FieldOrder::default(),
+            tick_millis: 1,
+        })
+    }
+
+    /// Set the order of the shard and sequence fields
+    ///
+    /// ```rust
+    /// use chronoflake::{FieldOrder, Layout};
+    ///
+    /// let layout = Layout::new(39, 16, 8)
+    ///     .unwrap()
+    ///     .with_order(FieldOrder::SequenceShard);
+    /// ```
+    pub fn with_order(mut self, order: FieldOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Set the length of one timestamp unit
+    ///
+    /// The tick must be a whole number of milliseconds. IDs issued within the same
+    /// tick share a timestamp and are told apart by their sequence number. The
+    /// largest timestamp must come to at most `i64::MAX` milliseconds, leaving
+    /// room to add an epoch.
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use chronoflake::Layout;
+    ///
+    /// // Seconds resolution leaves room for 16 bits of shard
+    /// let layout = Layout::new(35, 16, 12)
+    ///     .unwrap()
+    ///     .with_tick(Duration::from_secs(1))
+    ///     .unwrap();
+    ///
+    /// assert!(Layout::DEFAULT.with_tick(Duration::from_micros(1500)).is_err());
+    /// ```
+    pub fn with_tick(mut self, tick: Duration) -> Result<Self, Error> {
+        if tick < Duration::from_millis(1) || !tick.subsec_nanos().is_multiple_of(1_000_000) {
+            return Err(Error::InvalidLayout(format!(
+                "tick must be a whole number of milliseconds, got {tick:?}"
+            )));
+        }
+
+        let tick_millis = u64::try_from(tick.as_millis())
+            .ok()
+            .filter(|&tick| {
+                self.max_timestamp()
+                    .checked_mul(tick)
+                    .is_some_and(|span| span <= i64::MAX as u64)
+            })
+            .ok_or_else(|| {
+                Error::InvalidLayout(format!(
+                    "a tick of {tick:?} overflows a {}-bit timestamp",
+                    self.timestamp_bits
+                ))
+            })?;
+
+        self.tick_millis = tick_millis;
+        Ok(self)
+    }
+
+    /// Width of the timestamp field
+    pub fn timestamp_bits(&self) -> u8 {
+        self.timestamp_bits
+    }
+
+    /// Width of the shard field
+    pub fn shard_bits(&self) -> u8 {
+        self.shard_bits
+    }
+
+    /// Width of the sequence field
+    pub fn sequence_bits(&self) -> u8 {
+        self.sequence_bits
+    }
+
+    /// Order of the shard and sequence fields
+    pub fn order(&self) -> FieldOrder {
+        self.order
+    }
+
+    /// Length of one timestamp unit
+    pub fn tick(&self) -> Duration {
+        Duration::from_millis(self.tick_millis)
+    }
+
+    /// Largest timestamp that fits in the layout, in ticks
+    pub fn max_timestamp(&self) -> u64 {
+        (1 << self.timestamp_bits) - 1
+    }
+
+    /// Largest shard ID that fits in the layout
+    pub fn max_shard_id(&self) -> u16 {
+        ((1u32 << self.shard_bits) - 1) as u16
+    }
+
+    /// Largest sequence number that fits in the layout
+    pub fn max_sequence(&self) -> u16 {
+        ((1u32 << self.sequence_bits) - 1) as u16
+    }
+
+    /// Length of one timestamp unit in milliseconds
+    pub(crate) fn tick_millis(&self) -> u64 {
+        self.tick_millis
+    }
+
+    /// Number of whole ticks in a span of milliseconds
+    pub(crate) fn to_ticks(self, millis: u64) -> u64 {
+        millis / self.tick_millis
+    }
+
+    /// Number of milliseconds in a span of ticks
+    pub(crate) fn to_millis(self, ticks: u64) -> u64 {
+        ticks * self.tick_millis
+    }
+
+    /// Pack the fields into an ID, truncating any that are too wide
+    pub(crate) fn compose(&self, timestamp: u64, shard_id: u16, sequence: u16) -> u64 {
+        ((timestamp & self.max_timestamp()) << self.timestamp_shift())
+            | ((shard_id & self.max_shard_id()) as u64) << self.shard_shift()
+            | ((sequence & self.max_sequence()) as u64) << self.sequence_shift()
+    }
+
+    /// Timestamp field of an ID, in ticks
+    pub(crate) fn timestamp(&self, id: u64) -> u64 {
+        (id >> self.timestamp_shift()) & self.max_timestamp()
+    }
+
+    /// Shard field of an ID
+    pub(crate) fn shard_id(&self, id: u64) -> u16 {
+        (id >> self.shard_shift()) as u16 & self.max_shard_id()
+    }
+
+    /// Sequence field of an ID
+    pub(crate) fn sequence(&self, id: u64) -> u16 {
+        (id >> self.sequence_shift()) as u16 & self.max_sequence()
+    }
+
+    fn timestamp_shift(&self) -> u8 {
+        self.shard_bits + self.sequence_bits
+    }
+
+    fn shard_shift(&self) -> u8 {
+        match self.order {
+            FieldOrder::ShardSequence => self.sequence_bits,
+            FieldOrder::SequenceShard => 0,
+        }
+    }
+
+    fn sequence_shift(&self) -> u8 {
+        match self.order {
+            FieldOrder::ShardSequence => 0,
+            FieldOrder::SequenceShard => self.shard_bits,
+        }
+    }
+}
+
+/// Unvalidated layout used to check deserialized widths
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct LayoutFields {
+    timestamp_bits: u8,
+    shard_bits: u8,
+    sequence_bits: u8,
+    #[serde(default)]
+    order: FieldOrder,
+    #[serde(default = "default_tick")]
+    tick_millis: u64,
+}
+
+#[cfg(feature = "serde")]
+fn default_tick() -> u64 {
+    1
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<LayoutFields> for Layout {
+    type Error = Error;
+
+    fn try_from(fields: LayoutFields) -> Result<Self, Self::Error> {
+        Self::new(
+            fields.timestamp_bits,
+            fields.shard_bits,
+            fields.sequence_bits,
+        )?
+        .with_order(fields.order)
+        .with_tick(Duration::from_millis(fields.tick_millis))
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}",
+            self.timestamp_bits, self.shard_bits, self.sequence_bits
+        )?;
+
+        if self.order == FieldOrder::SequenceShard {
+            write!(f, ",sequence-first")?;
+        }
+
+        match self.tick_millis {
+            1 => {}
+            ms if ms % 1000 == 0 => write!(f, ",tick={}s", ms / 1000)?,
+            ms => write!(f, ",tick={ms}ms")?,
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for Layout {
+    type Err = Error;
+
+    /// Parse a layout written as `timestamp/shard/sequence` bit widths, e.g. `41/10/12`,
+    /// optionally followed by `,sequence-first` for [`FieldOrder::SequenceShard`]
+    /// and `,tick=10ms` or `,tick=1s` for the timestamp resolution
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid =
+            || Error::InvalidLayout(format!("expected timestamp/shard/sequence, got {s:?}"));
+
+        let mut options = s.split(',');
+        let widths = options.next().unwrap_or_default();
+        let mut parts = widths.split('/').map(|part| part.trim().parse::<u8>());
+        let mut layout = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(timestamp)), Some(Ok(shard)), Some(Ok(sequence)), None) => {
+                Self::new(timestamp, shard, sequence)?
+            }
+            _ => return Err(invalid()),
+        };
+
+        for option in options {
+            match option.trim() {
+                "sequence-first" => layout.order = FieldOrder::SequenceShard,
+                option => {
+                    let tick = option.strip_prefix("tick=").ok_or_else(invalid)?;
+                    let tick = match tick.strip_suffix("ms") {
+                        Some(ms) => ms.parse().map(Duration::from_millis),
+                        None => tick
+                            .strip_suffix('s')
+                            .ok_or_else(invalid)?
+                            .parse()
+                            .map(Duration::from_secs),
+                    };
+                    layout = layout.with_tick(tick.map_err(|_| invalid())?)?;
+                }
+            }
+        }
+
+        Ok(layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_matches_snowflake() {
+        let layout = Layout::default();
+        assert_eq!(layout.max_timestamp(), 0x1FFFFFFFFFF);
+        assert_eq!(layout.max_shard_id(), 0x3FF);
+        assert_eq!(layout.max_sequence(), 0xFFF);
+        assert_eq!(layout.compose(1234, 49, 7), (1234 << 22) | (49 << 12) | 7);
+    }
+
+    #[test]
+    fn custom_round_trip() {
+        let layout = Layout::new(39, 14, 10).unwrap();
+        let id = layout.compose(0x7FFFFFFFFF, 9000, 1023);
+        assert!(id <= i64::MAX as u64);
+        assert_eq!(layout.timestamp(id), 0x7FFFFFFFFF);
+        assert_eq!(layout.shard_id(id), 9000);
+        assert_eq!(layout.sequence(id), 1023);
+
+        let layout = Layout::new(47, 0, 16).unwrap();
+        let id = layout.compose(5, 0, u16::MAX);
+        assert_eq!(layout.sequence(id), u16::MAX);
+        assert_eq!(layout.shard_id(id), 0);
+    }
+
+    #[test]
+    fn invalid_layouts() {
+        assert!(Layout::new(41, 10, 13).is_err());
+        assert!(Layout::new(42, 10, 12).is_err());
+        assert!(Layout::new(0, 31, 32).is_err());
+        assert!(Layout::new(30, 17, 16).is_err());
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let layout: Layout = "39/14/10".parse().unwrap();
+        assert_eq!(layout, Layout::new(39, 14, 10).unwrap());
+        assert_eq!(Layout::DEFAULT.to_string(), "41/10/12");
+
+        assert!("41/10".parse::<Layout>().is_err());
+        assert!("41/10/12/0".parse::<Layout>().is_err());
+        assert!("41/ten/12".parse::<Layout>().is_err());
+        assert!("41/10/13".parse::<Layout>().is_err());
+        assert!("41/10/12,shard-first".parse::<Layout>().is_err());
+
+        let layout = Layout::new(39, 16, 8)
+            .unwrap()
+            .with_order(FieldOrder::SequenceShard);
+        assert_eq!(layout.to_string(), "39/16/8,sequence-first");
+        assert_eq!(layout.to_string().parse::<Layout>().unwrap(), layout);
+
+        let layout: Layout = "35/16/12,tick=1s".parse().unwrap();
+        assert_eq!(layout.tick(), Duration::from_secs(1));
+        assert_eq!(layout.to_string(), "35/16/12,tick=1s");
+
+        let layout = "39/16/8,sequence-first,tick=10ms"
+            .parse::<Layout>()
+            .unwrap();
+        assert_eq!(layout.tick(), Duration::from_millis(10));
+        assert_eq!(layout.to_string(), "39/16/8,sequence-first,tick=10ms");
+
+        assert!("41/10/12,tick=0ms".parse::<Layout>().is_err());
+        assert!("41/10/12,tick=10".parse::<Layout>().is_err());
+        assert!("41/10/12,tick=fast".parse::<Layout>().is_err());
+    }
+
+    #[test]
+    fn tick_conversion() {
+        let layout = Layout::DEFAULT
+            .with_tick(Duration::from_millis(10))
+            .unwrap();
+        assert_eq!(layout.to_ticks(1234), 123);
+        assert_eq!(layout.to_millis(123), 1230);
+
+        assert!(Layout::DEFAULT.with_tick(Duration::ZERO).is_err());
+        assert!(Layout::DEFAULT
+            .with_tick(Duration::from_nanos(10_500_000))
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_ticks() {
+        let layout = Layout::new(63, 0, 0).unwrap();
+        assert!(layout.with_tick(Duration::from_millis(1)).is_ok());
+        assert!(layout.with_tick(Duration::from_secs(1)).is_err());
+        assert!("63/0/0,tick=1s".parse::<Layout>().is_err());
+
+        assert!(Layout::DEFAULT.with_tick(Duration::from_secs(4000)).is_ok());
+        assert!(Layout::DEFAULT
+            .with_tick(Duration::from_secs(5000))
+            .is_err());
+
+        // Too long to count in milliseconds at all
+        assert!(Layout::DEFAULT
+            .with_tick(Duration::from_secs(u64::MAX))
+            .is_err());
+    }
+
+    #[test]
+    fn sequence_first_order() {
+        let layout = Layout::new(39, 16, 8)
+            .unwrap()
+            .with_order(FieldOrder::SequenceShard);
+        let id = layout.compose(1234, 0xBEEF, 0xAB);
+        assert_eq!(id, (1234 << 24) | (0xAB << 16) | 0xBEEF);
+        assert_eq!(layout.timestamp(id), 1234);
+        assert_eq!(layout.shard_id(id), 0xBEEF);
+        assert_eq!(layout.sequence(id), 0xAB);
+    }
+}
@@ -0,0 +1,194 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use crate::{Error, Layout};
+
+/// Exclusive claim on a shard ID, shared by the processes on one host
+///
+/// Each shard ID in the layout has a lock file in a common directory. Acquiring a
+/// lease takes an advisory lock (`flock` on Linux) on the first free file, which
+/// the operating system releases when the lease is dropped or the process dies.
+/// Processes that do not use the same directory are not coordinated.
+///
+/// ```rust,no_run
+/// use chronoflake::IdGenerator;
+///
+/// let cf = IdGenerator::builder()
+///     .with_shard_lease("/run/myapp/shards")
+///     .build()
+///     .unwrap();
+/// println!("Generating IDs as shard {}", cf.shard_id);
+/// ```
+#[derive(Debug)]
+pub struct ShardLease {
+    shard_id: u16,
+    path: PathBuf,
+    file: File,
+}
+
+impl ShardLease {
+    /// Claim the lowest shard ID in the layout that no other process holds
+    ///
+    /// The directory is created if it does not exist. Returns
+    /// [`Error::NoFreeShard`] if every shard ID is already leased.
+    pub fn acquire(dir: impl AsRef<Path>, layout: &Layout) -> Result<Self, Error> {
+        Self::acquire_excluding(dir.as_ref(), layout, None)
+    }
+
+    /// Like [`acquire`](Self::acquire), skipping shard IDs reserved for backfilling
+    ///
+    /// Returns [`Error::NoUnreservedShard`] if every other shard ID is already leased.
+    pub(crate) fn acquire_excluding(
+        dir: &Path,
+        layout: &Layout,
+        reserved: Option<&RangeInclusive<u16>>,
+    ) -> Result<Self, Error> {
+        fs::create_dir_all(dir).map_err(|e| lease_error(dir, e))?;
+
+        let max = layout.max_shard_id();
+        let free = (0..=max).filter(|shard_id| !reserved.is_some_and(|r| r.contains(shard_id)));
+        for shard_id in free {
+            let path = dir.join(format!("shard-{shard_id}.lock"));
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&path)
+                .map_err(|e| lease_error(&path, e))?;
+
+            match file.try_lock() {
+                Ok(()) => {}
+                Err(TryLockError::WouldBlock) => continue,
+                Err(TryLockError::Error(e)) => return Err(lease_error(&path, e)),
+            }
+
+            // Record the holder to make the directory easier to inspect
+            file.set_len(0)
+                .and_then(|()| writeln!(&file, "{}", std::process::id()))
+                .map_err(|e| lease_error(&path, e))?;
+
+            return Ok(Self {
+                shard_id,
+                path,
+                file,
+            });
+        }
+
+        Err(match reserved {
+            Some(reserved) => Error::NoUnreservedShard {
+                max,
+                reserved: reserved.clone(),
+            },
+            None => Error::NoFreeShard { max },
+        })
+    }
+
+    /// The leased shard ID
+    pub fn shard_id(&self) -> u16 {
+        self.shard_id
+    }
+
+    /// Path of the lock file held for the shard ID
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl PartialEq for ShardLease {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Eq for ShardLease {}
+
+impl Hash for ShardLease {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+    }
+}
+
+impl Drop for ShardLease {
+    fn drop(&mut self) {
+        // Closing the file releases the lock anyway, unlocking just makes it explicit
+        let _ = self.file.unlock();
+    }
+}
+
+fn lease_error(path: &Path, reason: impl ToString) -> Error {
+    Error::ShardLease {
+        path: path.to_path_buf(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempPath;
+
+    #[test]
+    fn leases_are_exclusive() {
+        let dir = TempPath::new("exclusive.leases");
+        let layout = Layout::new(45, 2, 16).unwrap();
+
+        let mut leases: Vec<_> = (0..4)
+            .map(|_| ShardLease::acquire(&dir, &layout).unwrap())
+            .collect();
+        let ids: Vec<_> = leases.iter().map(ShardLease::shard_id).collect();
+        assert_eq!(ids, [0, 1, 2, 3]);
+
+        assert_eq!(
+            ShardLease::acquire(&dir, &layout).unwrap_err(),
+            Error::NoFreeShard { max: 3 }
+        );
+
+        // Dropping a lease frees its shard for the next process
+        leases.remove(2);
+        let lease = ShardLease::acquire(&dir, &layout).unwrap();
+        assert_eq!(lease.shard_id(), 2);
+        assert_eq!(
+            fs::read_to_string(lease.path()).unwrap().trim(),
+            std::process::id().to_string()
+        );
+    }
+
+    #[test]
+    fn skips_reserved_shards() {
+        let dir = TempPath::new("reserved.leases");
+        let layout = Layout::new(45, 2, 16).unwrap();
+        let reserved = 1..=2;
+
+        let leases: Vec<_> = (0..2)
+            .map(|_| ShardLease::acquire_excluding(&dir, &layout, Some(&reserved)).unwrap())
+            .collect();
+        let ids: Vec<_> = leases.iter().map(ShardLease::shard_id).collect();
+        assert_eq!(ids, [0, 3]);
+
+        let err = ShardLease::acquire_excluding(&dir, &layout, Some(&reserved)).unwrap_err();
+        assert_eq!(
+            err,
+            Error::NoUnreservedShard {
+                max: 3,
+                reserved: 1..=2
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "every shard ID from 0 to 3 outside the 1-2 reserved for backfilling is already leased"
+        );
+    }
+
+    #[test]
+    fn reports_unusable_directory() {
+        let dir = TempPath::new("unusable.leases");
+        fs::write(&dir, "not a directory").unwrap();
+
+        let err = ShardLease::acquire(&dir, &Layout::DEFAULT).unwrap_err();
+        assert!(matches!(err, Error::ShardLease { .. }));
+    }
+}
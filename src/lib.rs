@@ -12,34 +12,151 @@
 //!     let mut cf = IdGenerator::new(14)
 //!         .with_epoch(PROJECT_EPOCH);
 //!
-//!     let id = cf.generate_id();
+//!     let id = cf.generate_id().unwrap();
 //!     println!("ID: {id}"); // 1704967240656416804
 //! }
 //! ```
-use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod atomic;
+mod backfill;
+mod builder;
+mod clock;
+mod encoding;
+mod error;
+#[cfg(feature = "http")]
+pub mod http;
+mod id;
+mod ksuid;
+mod layout;
+mod lease;
+mod metrics;
+mod preset;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+mod random;
+mod range;
+mod scheme;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod shard;
+mod state;
+mod store;
+#[cfg(test)]
+mod test_util;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+mod ulid;
+mod uuid;
+
+pub use atomic::AtomicIdGenerator;
+pub use backfill::BackfillGenerator;
+pub use builder::IdGeneratorBuilder;
+pub use clock::{Clock, MockClock, SystemClock};
+pub use encoding::Encoding;
+pub use error::Error;
+pub use id::Chronoflake;
+pub use ksuid::{Ksuid, KsuidGenerator, KSUID_EPOCH};
+pub use layout::{FieldOrder, Layout, ID_BITS};
+pub use lease::ShardLease;
+pub use metrics::Metrics;
+pub use preset::Preset;
+pub use range::{Buckets, IdRange};
+pub use scheme::IdScheme;
+pub use shard::ShardSource;
+pub use store::{StateFile, DEFAULT_RESERVATION};
+pub use ulid::{Ulid, UlidGenerator};
+pub use uuid::UuidV7Generator;
 
 /// Default time epoch to use (Twitter Epoch)
 pub const DEFAULT_EPOCH: u64 = 1288834974657;
 
+/// How the generator waits for the next millisecond once the sequence is exhausted
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum WaitStrategy {
+    /// Busy-wait on the clock (lowest latency, burns a core while waiting)
+    #[default]
+    Spin,
+
+    /// Yield to the scheduler between clock reads
+    Yield,
+
+    /// Sleep for the given duration between clock reads
+    Sleep(Duration),
+}
+
+/// What the generator does when the clock reports a time earlier than the last issued ID
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub enum RollbackPolicy {
+    /// Return [`Error::ClockMovedBackwards`]
+    Error,
+
+    /// Block until the clock catches up, failing if it is behind by more than the given duration
+    Wait(Duration),
+
+    /// Keep issuing IDs from the last timestamp, borrowing future milliseconds as needed
+    Continue,
+}
+
+impl Default for RollbackPolicy {
+    fn default() -> Self {
+        Self::Wait(Duration::from_secs(1))
+    }
+}
+
 /// Unique ID generator
+///
+/// With the `serde` feature the generator's settings, last issued ID and metrics
+/// can be serialized; the clock is not and is recreated with its `Default`.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
-pub struct IdGenerator {
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct IdGenerator<C = SystemClock> {
     /// Time Epoch to use (in milliseconds)
     pub epoch: u64,
 
     /// Machine or Shard ID
     pub shard_id: u16,
 
-    /// Sequence number to track sequential ID generated within a timeframe
+    /// Last sequence number issued within the current timeframe
     pub sequence: u16,
 
-    /// Timeframe in which sequences can increase
+    /// Timestamp (in milliseconds) of the last issued ID
     pub timestamp: u64,
+
+    /// How to wait for the next millisecond when the sequence is exhausted
+    pub wait_strategy: WaitStrategy,
+
+    /// How to react when the clock goes backwards
+    pub rollback_policy: RollbackPolicy,
+
+    /// Bit layout of the generated IDs
+    pub layout: Layout,
+
+    /// Source of the current time
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub clock: C,
+
+    /// File persisting the high-water timestamp across restarts
+    pub state_file: Option<StateFile>,
+
+    /// Lease on the shard ID, released once every clone of the generator is dropped
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub lease: Option<Arc<ShardLease>>,
+
+    /// Counters of issued IDs, waits and clock anomalies
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub metrics: Metrics,
 }
 
 impl IdGenerator {
     /// Create a new Chronoflake ID Generator
     ///
+    /// The shard ID is truncated to fit the layout, use [`IdGenerator::builder`]
+    /// to have it checked instead.
+    ///
     /// ```rust
     /// use chronoflake::IdGenerator;
     ///
@@ -50,7 +167,50 @@ impl IdGenerator {
             epoch: DEFAULT_EPOCH,
             shard_id,
             sequence: 0,
-            timestamp: Utc::now().timestamp_millis() as u64,
+            timestamp: 0,
+            wait_strategy: WaitStrategy::default(),
+            rollback_policy: RollbackPolicy::default(),
+            layout: Layout::default(),
+            clock: SystemClock,
+            state_file: None,
+            lease: None,
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Create a builder that validates the generator's settings
+    ///
+    /// ```rust
+    /// use chronoflake::IdGenerator;
+    ///
+    /// let mut cf = IdGenerator::builder().with_shard_id(16).build().unwrap();
+    /// ```
+    pub fn builder() -> IdGeneratorBuilder {
+        IdGeneratorBuilder::new()
+    }
+}
+
+impl<C: Clock> IdGenerator<C> {
+    /// Use a different source of time for the generator
+    ///
+    /// ```rust
+    /// use chronoflake::{IdGenerator, MockClock, DEFAULT_EPOCH};
+    ///
+    /// let mut cf = IdGenerator::new(16).with_clock(MockClock::new(DEFAULT_EPOCH));
+    /// ```
+    pub fn with_clock<D: Clock>(self, clock: D) -> IdGenerator<D> {
+        IdGenerator {
+            epoch: self.epoch,
+            shard_id: self.shard_id,
+            sequence: 0,
+            timestamp: 0,
+            wait_strategy: self.wait_strategy,
+            rollback_policy: self.rollback_policy,
+            layout: self.layout,
+            clock,
+            state_file: self.state_file,
+            lease: self.lease,
+            metrics: Metrics::default(),
         }
     }
 
@@ -66,49 +226,507 @@ impl IdGenerator {
         self
     }
 
+    /// Set the bit layout of the generated IDs
+    ///
+    /// ```rust
+    /// use chronoflake::{IdGenerator, Layout};
+    ///
+    /// let mut cf = IdGenerator::new(9000).with_layout(Layout::new(39, 14, 10).unwrap());
+    /// ```
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Use the epoch and layout of a well-known ID scheme
+    ///
+    /// ```rust
+    /// use chronoflake::{IdGenerator, Preset};
+    ///
+    /// let mut cf = IdGenerator::new(1341).with_preset(Preset::INSTAGRAM);
+    /// ```
+    pub fn with_preset(self, preset: Preset) -> Self {
+        self.with_epoch(preset.epoch).with_layout(preset.layout)
+    }
+
+    /// Set how the generator waits when the sequence is exhausted
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use chronoflake::{IdGenerator, WaitStrategy};
+    ///
+    /// let mut cf = IdGenerator::new(16)
+    ///     .with_wait_strategy(WaitStrategy::Sleep(Duration::from_micros(100)));
+    /// ```
+    pub fn with_wait_strategy(mut self, wait_strategy: WaitStrategy) -> Self {
+        self.wait_strategy = wait_strategy;
+        self
+    }
+
+    /// Set how the generator reacts when the clock goes backwards
+    ///
+    /// ```rust
+    /// use chronoflake::{IdGenerator, RollbackPolicy};
+    ///
+    /// let mut cf = IdGenerator::new(16).with_rollback_policy(RollbackPolicy::Error);
+    /// ```
+    pub fn with_rollback_policy(mut self, rollback_policy: RollbackPolicy) -> Self {
+        self.rollback_policy = rollback_policy;
+        self
+    }
+
     /// Generate a unique ID
     ///
+    /// If every sequence number for the current millisecond has been used, this
+    /// waits for the next millisecond according to the [`WaitStrategy`]. If the
+    /// clock has gone backwards the [`RollbackPolicy`] decides whether to wait,
+    /// fail or carry on from the last timestamp.
+    ///
     /// ```rust
     /// use chronoflake::IdGenerator;
     ///
     /// let mut cf = IdGenerator::new(16).with_epoch(1488432924251);
-    /// let id = cf.generate_id();
+    /// let id = cf.generate_id().unwrap();
     /// println!("ID: {id}"); // 1704967240656416804
     /// ```
-    pub fn generate_id(&mut self) -> u64 {
-        let now = Utc::now().timestamp_millis() as u64;
-        if now > self.timestamp + 1 {
-            self.timestamp = now;
-            self.sequence = 0;
+    pub fn generate_id(&mut self) -> Result<u64, Error> {
+        let mut waiting = false;
+        loop {
+            match self.try_generate_id() {
+                Ok(id) => return Ok(id),
+                Err(err) => self.recover(err, waiting)?,
+            }
+            waiting = true;
+        }
+    }
+
+    /// Generate a unique ID without blocking
+    ///
+    /// Returns [`Error::SequenceExhausted`] if every sequence number for the current
+    /// millisecond has been used, and [`Error::ClockMovedBackwards`] if the clock is
+    /// behind the last issued ID (unless the policy is [`RollbackPolicy::Continue`]).
+    ///
+    /// ```rust
+    /// use chronoflake::{Error, IdGenerator};
+    ///
+    /// let mut cf = IdGenerator::new(16);
+    /// match cf.try_generate_id() {
+    ///     Ok(id) => println!("ID: {id}"),
+    ///     Err(Error::SequenceExhausted) => println!("Try again next millisecond"),
+    ///     Err(e) => println!("Failed to generate ID: {e}"),
+    /// }
+    /// ```
+    pub fn try_generate_id(&mut self) -> Result<u64, Error> {
+        let (ts, sequence, _) = self.try_claim(1)?;
+        let id = self.layout.compose(ts, self.shard_id, sequence);
+
+        Ok(id)
+    }
+
+    /// Generate `count` unique IDs in increasing order
+    ///
+    /// See [`fill`](Self::fill).
+    ///
+    /// ```rust
+    /// use chronoflake::IdGenerator;
+    ///
+    /// let mut cf = IdGenerator::new(16);
+    /// let ids = cf.generate_batch(10_000).unwrap();
+    /// assert!(ids.windows(2).all(|w| w[0] < w[1]));
+    /// ```
+    pub fn generate_batch(&mut self, count: usize) -> Result<Vec<u64>, Error> {
+        let mut ids = vec![0; count];
+        self.fill(&mut ids)?;
+        Ok(ids)
+    }
+
+    /// Fill a slice with unique IDs in increasing order
+    ///
+    /// The clock is read once per tick and every remaining sequence number in that
+    /// tick is claimed at once, moving on to the next tick until the slice is full.
+    /// Waits in the same way as [`generate_id`](Self::generate_id); if an error is
+    /// returned the slice may have been partly filled.
+    pub fn fill(&mut self, ids: &mut [u64]) -> Result<(), Error> {
+        let mut filled = 0;
+        let mut waiting = false;
+        while filled < ids.len() {
+            match self.try_claim(ids.len() - filled) {
+                Ok((ts, first, last)) => {
+                    for sequence in first..=last {
+                        ids[filled] = self.layout.compose(ts, self.shard_id, sequence);
+                        filled += 1;
+                    }
+                    waiting = false;
+                }
+                Err(err) => {
+                    self.recover(err, waiting)?;
+                    waiting = true;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Claim up to `count` sequence numbers in the current tick, returning the
+    /// tick and the first and last sequence claimed
+    fn try_claim(&mut self, count: usize) -> Result<(u64, u16, u16), Error> {
+        let now = self.clock.now_millis();
+        self.metrics.record_reading(now);
+        let (timestamp, first, last) = state::advance_by(
+            &self.layout,
+            self.rollback_policy,
+            self.epoch,
+            self.timestamp,
+            self.sequence,
+            now,
+            count,
+        )?;
+
+        if let Some(state_file) = &mut self.state_file {
+            state_file.reserve(timestamp)?;
+        }
+        (self.timestamp, self.sequence) = (timestamp, last);
+        self.metrics.record_issued((last - first) as u64 + 1);
+
+        Ok((self.layout.to_ticks(timestamp - self.epoch), first, last))
+    }
+
+    /// Wait out a failed attempt at generating IDs and record it in the metrics
+    ///
+    /// `waiting` is whether the previous attempt failed too, so that each wait for
+    /// the next tick counts as one exhaustion however many attempts it takes.
+    fn recover(&mut self, err: Error, waiting: bool) -> Result<(), Error> {
+        let exhausted = !waiting && err == Error::SequenceExhausted;
+
+        // Timed on the generator's own clock, so mocked waits are measured too
+        let started = self.clock.now_millis();
+        state::recover(
+            &self.clock,
+            &self.layout,
+            self.epoch,
+            self.wait_strategy,
+            self.rollback_policy,
+            err,
+        )?;
+        let waited = self.clock.now_millis().saturating_sub(started);
+        self.metrics
+            .record_wait(Duration::from_millis(waited), exhausted);
+
+        Ok(())
+    }
+
+    /// Check that the shard ID fits the layout and the epoch suits the current time
+    ///
+    /// ```rust
+    /// use chronoflake::IdGenerator;
+    ///
+    /// assert!(IdGenerator::new(16).validate().is_ok());
+    /// assert!(IdGenerator::new(4096).validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<(), Error> {
+        let max = self.layout.max_shard_id();
+        if self.shard_id > max {
+            return Err(Error::ShardIdTooLarge {
+                shard_id: self.shard_id,
+                max,
+            });
         }
 
-        let ts = now - self.epoch;
-        let id = ((ts & 0x1FFFFFFFFFF) << 22)
-            | ((self.shard_id as u64 & 0x3FF) << 12)
-            | (self.sequence as u64 & 0xFFF);
+        let now = self.clock.now_millis();
+        if self.epoch > now {
+            return Err(Error::EpochInFuture { epoch: self.epoch });
+        }
 
-        self.sequence += 1;
+        let max = self.layout.max_timestamp();
+        if self.layout.to_ticks(now - self.epoch) > max {
+            return Err(Error::TimestampOverflow { max });
+        }
 
-        id
+        Ok(())
+    }
+
+    /// Pick up from the high-water timestamp in the state file, if there is one
+    ///
+    /// Everything up to the high-water mark is treated as already issued. If the
+    /// clock is behind it by no more than the reservation, as after an ordinary
+    /// restart, the generator waits for the clock to reach it. Otherwise the
+    /// clock has gone backwards and the rollback policy decides whether to wait
+    /// for it now, fail, or carry on from the mark.
+    pub(crate) fn restore(&mut self) -> Result<(), Error> {
+        let Some(state_file) = &mut self.state_file else {
+            return Ok(());
+        };
+
+        let Some(high_water) = state_file.load()? else {
+            return Ok(());
+        };
+        let reservation = state_file.reservation();
+
+        // Everything in the tick holding the high-water mark counts as issued
+        let since_epoch = high_water.saturating_sub(self.epoch);
+        let high_water = self.epoch + self.layout.to_millis(self.layout.to_ticks(since_epoch));
+        if high_water > self.timestamp {
+            self.timestamp = high_water;
+            self.sequence = self.layout.max_sequence();
+        }
+
+        let now = self.clock.now_millis();
+        if now >= self.timestamp || self.rollback_policy == RollbackPolicy::Continue {
+            return Ok(());
+        }
+
+        let behind = Duration::from_millis(self.timestamp - now);
+        if behind <= reservation {
+            self.clock.sleep(behind);
+        } else {
+            state::recover(
+                &self.clock,
+                &self.layout,
+                self.epoch,
+                self.wait_strategy,
+                self.rollback_policy,
+                Error::ClockMovedBackwards {
+                    by: self.timestamp - now,
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Decode an ID generated with this generator's epoch and layout
+    ///
+    /// ```rust
+    /// use chronoflake::IdGenerator;
+    ///
+    /// let mut cf = IdGenerator::new(16);
+    /// let raw = cf.generate_id().unwrap();
+    /// let id = cf.decode(raw);
+    /// assert_eq!(id.shard_id(), 16);
+    /// ```
+    pub fn decode(&self, id: u64) -> Chronoflake {
+        Chronoflake::new(id)
+            .with_epoch(self.epoch)
+            .with_layout(self.layout)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
 
     #[test]
     fn mass_unique() {
-        let mut cf = IdGenerator::new(49);
+        let mut cf = IdGenerator::new(49).with_clock(MockClock::new(DEFAULT_EPOCH + 1000));
 
         let mut prev_id: u64 = 0;
         for _ in 0..50_000_000 {
-            let id = cf.generate_id();
+            let id = cf.generate_id().unwrap();
             assert!(prev_id != id);
             prev_id = id;
         }
     }
 
+    #[test]
+    fn burst_is_globally_unique() {
+        let mut cf = IdGenerator::new(49);
+
+        let count = 10 * (cf.layout.max_sequence() as usize + 1);
+        let ids: HashSet<u64> = (0..count).map(|_| cf.generate_id().unwrap()).collect();
+        assert_eq!(ids.len(), count);
+    }
+
+    #[test]
+    fn try_generate_reports_exhaustion() {
+        let mut cf = IdGenerator::new(49).with_clock(MockClock::new(DEFAULT_EPOCH + 1000));
+
+        let issued = (0..10_000_000)
+            .take_while(|_| cf.try_generate_id().is_ok())
+            .count();
+        assert_eq!(issued, cf.layout.max_sequence() as usize + 1);
+        assert_eq!(cf.try_generate_id(), Err(Error::SequenceExhausted));
+        assert_eq!(cf.sequence, cf.layout.max_sequence());
+    }
+
+    #[test]
+    fn rollback_error_policy() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 100_000);
+        let mut cf = IdGenerator::new(49)
+            .with_clock(clock.clone())
+            .with_rollback_policy(RollbackPolicy::Error);
+        cf.generate_id().unwrap();
+
+        clock.rewind(Duration::from_secs(60));
+        assert_eq!(
+            cf.generate_id(),
+            Err(Error::ClockMovedBackwards { by: 60_000 })
+        );
+    }
+
+    #[test]
+    fn rollback_wait_policy() {
+        let mut cf = IdGenerator::new(49)
+            .with_rollback_policy(RollbackPolicy::Wait(Duration::from_millis(500)));
+
+        cf.generate_id().unwrap();
+        cf.timestamp += 20;
+        let last = cf.timestamp;
+        let id = cf.generate_id().unwrap();
+        assert!(cf.timestamp >= last);
+        assert_eq!(id >> 22, cf.timestamp - cf.epoch);
+
+        cf.timestamp += 60_000;
+        let err = cf.generate_id().unwrap_err();
+        assert!(matches!(err, Error::ClockMovedBackwards { .. }));
+    }
+
+    #[test]
+    fn rollback_continue_policy() {
+        let mut cf = IdGenerator::new(49).with_rollback_policy(RollbackPolicy::Continue);
+        cf.generate_id().unwrap();
+        cf.timestamp += 60_000;
+        let logical = cf.timestamp;
+
+        let count = 3 * (cf.layout.max_sequence() as usize + 1);
+        let ids: Vec<u64> = (0..count).map(|_| cf.generate_id().unwrap()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(ids[0] >> 22, logical - cf.epoch);
+        assert_eq!(cf.timestamp, logical + 3);
+    }
+
+    #[test]
+    fn custom_layout() {
+        let layout = Layout::new(39, 14, 10).unwrap();
+        let mut cf = IdGenerator::new(9000).with_layout(layout);
+
+        let count = 10 * (layout.max_sequence() as usize + 1);
+        let ids: HashSet<u64> = (0..count).map(|_| cf.generate_id().unwrap()).collect();
+        assert_eq!(ids.len(), count);
+
+        let id = cf.decode(*ids.iter().next().unwrap());
+        assert_eq!(id.shard_id(), 9000);
+    }
+
+    #[test]
+    fn seconds_resolution() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 5_250);
+        let layout = Layout::new(35, 16, 12)
+            .unwrap()
+            .with_tick(Duration::from_secs(1))
+            .unwrap();
+        let mut cf = IdGenerator::new(40_000)
+            .with_layout(layout)
+            .with_clock(clock.clone());
+
+        let raw = cf.generate_id().unwrap();
+        assert_eq!(raw >> 28, 5);
+        assert_eq!(cf.decode(raw).timestamp(), DEFAULT_EPOCH + 5_000);
+        assert_eq!(cf.decode(raw).shard_id(), 40_000);
+
+        clock.advance(Duration::from_millis(500));
+        let raw = cf.generate_id().unwrap();
+        assert_eq!(cf.decode(raw).timestamp(), DEFAULT_EPOCH + 5_000);
+        assert_eq!(cf.decode(raw).sequence(), 1);
+
+        // Exhausting the sequence sleeps until the next second
+        while cf.try_generate_id().is_ok() {}
+        let raw = cf.generate_id().unwrap();
+        assert_eq!(clock.now_millis(), DEFAULT_EPOCH + 6_000);
+        assert_eq!(cf.decode(raw).timestamp(), DEFAULT_EPOCH + 6_000);
+        assert_eq!(cf.decode(raw).sequence(), 0);
+    }
+
+    #[test]
+    fn exhaustion_with_mock_clock() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let mut cf = IdGenerator::new(49).with_clock(clock.clone());
+
+        let max = cf.layout.max_sequence() as usize;
+        for _ in 0..=max {
+            cf.try_generate_id().unwrap();
+        }
+        assert_eq!(cf.try_generate_id(), Err(Error::SequenceExhausted));
+
+        let raw = cf.generate_id().unwrap();
+        let id = cf.decode(raw);
+        assert_eq!(id.timestamp(), DEFAULT_EPOCH + 1001);
+        assert_eq!(id.sequence(), 0);
+        assert_eq!(clock.now_millis(), DEFAULT_EPOCH + 1001);
+    }
+
+    #[test]
+    fn rollback_with_mock_clock() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 10_000);
+        let mut cf = IdGenerator::new(49)
+            .with_clock(clock.clone())
+            .with_rollback_policy(RollbackPolicy::Wait(Duration::from_secs(5)));
+        cf.generate_id().unwrap();
+
+        clock.rewind(Duration::from_secs(2));
+        let raw = cf.generate_id().unwrap();
+        let id = cf.decode(raw);
+        assert_eq!(id.timestamp(), DEFAULT_EPOCH + 10_000);
+        assert_eq!(clock.now_millis(), DEFAULT_EPOCH + 10_000);
+
+        clock.rewind(Duration::from_secs(6));
+        assert_eq!(
+            cf.generate_id(),
+            Err(Error::ClockMovedBackwards { by: 6000 })
+        );
+    }
+
+    #[test]
+    fn timestamp_overflow() {
+        let layout = Layout::new(31, 16, 16).unwrap();
+        let clock = MockClock::new(DEFAULT_EPOCH + layout.max_timestamp());
+        let mut cf = IdGenerator::new(49)
+            .with_layout(layout)
+            .with_clock(clock.clone());
+        assert!(cf.generate_id().is_ok());
+
+        clock.advance(Duration::from_millis(1));
+        assert_eq!(
+            cf.generate_id(),
+            Err(Error::TimestampOverflow {
+                max: layout.max_timestamp()
+            })
+        );
+    }
+
+    #[test]
+    fn clock_before_epoch() {
+        let mut cf = IdGenerator::new(49).with_epoch(u64::MAX);
+        assert_eq!(cf.generate_id(), Err(Error::ClockBeforeEpoch));
+    }
+
+    #[test]
+    fn batch_rolls_over_ticks() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let mut cf = IdGenerator::new(49).with_clock(clock.clone());
+        let single = cf.generate_id().unwrap();
+
+        let ids = cf.generate_batch(10_000).unwrap();
+        assert_eq!(ids.len(), 10_000);
+        assert!(single < ids[0]);
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        let per_tick = |ms| {
+            ids.iter()
+                .filter(|&&id| cf.decode(id).timestamp() == DEFAULT_EPOCH + ms)
+                .count()
+        };
+        assert_eq!(per_tick(1000), 4095);
+        assert_eq!(per_tick(1001), 4096);
+        assert_eq!(per_tick(1002), 10_000 - 4095 - 4096);
+        assert_eq!(cf.sequence as usize, 10_000 - 4095 - 4096 - 1);
+
+        let next = cf.generate_id().unwrap();
+        assert!(next > ids[9_999]);
+        assert!(cf.generate_batch(0).unwrap().is_empty());
+    }
+
     #[test]
     fn clone_works() {
         let cf = IdGenerator::new(49);
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+/// Counters describing how an [`IdGenerator`](crate::IdGenerator) has been running
+///
+/// Read a snapshot from the generator's `metrics` field. A steadily climbing
+/// `sequence_exhaustions` means the shard is generating IDs as fast as its
+/// layout allows and callers are being throttled.
+///
+/// ```rust
+/// use chronoflake::IdGenerator;
+///
+/// let mut cf = IdGenerator::new(16);
+/// cf.generate_batch(10_000).unwrap();
+///
+/// let metrics = cf.metrics;
+/// assert_eq!(metrics.ids_issued, 10_000);
+/// println!("Waited {:?} for the sequence to reset", metrics.wait_time);
+/// ```
+#[derive(Clone, Copy, Debug, Default, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(::serde::Serialize, ::serde::Deserialize))]
+pub struct Metrics {
+    /// Number of IDs issued
+    pub ids_issued: u64,
+
+    /// Number of times every sequence number in a tick was used up and the
+    /// generator waited for the next tick
+    pub sequence_exhaustions: u64,
+
+    /// Time spent waiting for the next tick or for the clock to catch up after
+    /// going backwards, as measured by the generator's clock in whole milliseconds
+    pub wait_time: Duration,
+
+    /// Number of times the clock read earlier than the reading before it
+    pub clock_rollbacks: u64,
+
+    /// Largest backwards jump of the clock
+    pub max_rollback: Duration,
+
+    /// Last clock reading, to spot the clock going backwards
+    last_reading: u64,
+}
+
+impl Metrics {
+    /// Record a clock reading (Unix milliseconds)
+    pub(crate) fn record_reading(&mut self, now: u64) {
+        if now < self.last_reading {
+            self.clock_rollbacks += 1;
+            let by = Duration::from_millis(self.last_reading - now);
+            self.max_rollback = self.max_rollback.max(by);
+        }
+        self.last_reading = now;
+    }
+
+    /// Record `count` newly issued IDs
+    pub(crate) fn record_issued(&mut self, count: u64) {
+        self.ids_issued += count;
+    }
+
+    /// Record time spent waiting out a failed attempt, which started a new wait
+    /// for the next tick if `exhausted`
+    pub(crate) fn record_wait(&mut self, waited: Duration, exhausted: bool) {
+        self.wait_time += waited;
+        if exhausted {
+            self.sequence_exhaustions += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdGenerator, MockClock, RollbackPolicy, DEFAULT_EPOCH};
+
+    #[test]
+    fn counts_exhaustion_per_tick() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let mut cf = IdGenerator::new(1).with_clock(clock);
+
+        cf.generate_batch(4096 * 3 + 1).unwrap();
+        for _ in 0..4096 {
+            cf.generate_id().unwrap();
+        }
+
+        assert_eq!(cf.metrics.ids_issued, 4096 * 4 + 1);
+        assert_eq!(cf.metrics.sequence_exhaustions, 4);
+        assert_eq!(cf.metrics.clock_rollbacks, 0);
+    }
+
+    #[test]
+    fn tracks_clock_rollbacks() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let mut cf = IdGenerator::new(1)
+            .with_clock(clock.clone())
+            .with_rollback_policy(RollbackPolicy::Continue);
+
+        cf.generate_id().unwrap();
+        clock.rewind(Duration::from_millis(5));
+        cf.generate_id().unwrap();
+        cf.generate_id().unwrap();
+        clock.rewind(Duration::from_millis(20));
+        cf.generate_id().unwrap();
+
+        assert_eq!(cf.metrics.clock_rollbacks, 2);
+        assert_eq!(cf.metrics.max_rollback, Duration::from_millis(20));
+        assert_eq!(cf.metrics.ids_issued, 4);
+
+        // Waiting for the clock to catch up counts once, however long it takes
+        cf.rollback_policy = RollbackPolicy::Wait(Duration::from_secs(1));
+        cf.generate_id().unwrap();
+        assert_eq!(cf.metrics.clock_rollbacks, 2);
+        assert_eq!(cf.metrics.sequence_exhaustions, 0);
+    }
+
+    #[test]
+    fn measures_waits_on_the_generator_clock() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let mut cf = IdGenerator::new(1)
+            .with_clock(clock.clone())
+            .with_rollback_policy(RollbackPolicy::Wait(Duration::from_secs(1)));
+
+        cf.generate_id().unwrap();
+        clock.rewind(Duration::from_millis(250));
+        cf.generate_id().unwrap();
+        assert_eq!(cf.metrics.wait_time, Duration::from_millis(250));
+
+        // Each exhausted tick waits one mocked millisecond for the next
+        cf.generate_batch(4096 * 2).unwrap();
+        assert_eq!(cf.metrics.sequence_exhaustions, 2);
+        assert_eq!(cf.metrics.wait_time, Duration::from_millis(252));
+    }
+}
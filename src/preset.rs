@@ -0,0 +1,177 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, FieldOrder, Layout, DEFAULT_EPOCH};
+
+/// Epoch and layout of a well-known Snowflake-style ID scheme
+///
+/// Schemes that use all 64 bits are given a timestamp one bit narrower so IDs
+/// stay positive. This decodes their IDs identically until the top bit is first
+/// used, which for Instagram is in 2046, Discord in 2084 and Mastodon in 6429.
+///
+/// ```rust
+/// use chronoflake::{Chronoflake, IdGenerator, Preset};
+///
+/// let id = Chronoflake::new(175928847299117063).with_preset(Preset::DISCORD);
+/// assert_eq!(id.timestamp(), 1462015105796);
+///
+/// let mut cf = IdGenerator::new(5).with_preset(Preset::SONYFLAKE);
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct Preset {
+    /// Name of the scheme
+    pub name: &'static str,
+
+    /// Epoch (in milliseconds) the scheme's timestamps are relative to
+    pub epoch: u64,
+
+    /// Bit layout of the scheme's IDs
+    pub layout: Layout,
+}
+
+impl Preset {
+    /// Twitter Snowflake: 41 bits of milliseconds, 10 bits of machine, 12 bits of sequence
+    pub const TWITTER: Self = Self {
+        name: "twitter",
+        epoch: DEFAULT_EPOCH,
+        layout: Layout::DEFAULT,
+    };
+
+    /// Discord: the Twitter layout with a 2015 epoch; the shard is the 5-bit
+    /// worker ID followed by the 5-bit process ID
+    pub const DISCORD: Self = Self {
+        name: "discord",
+        epoch: 1420070400000,
+        layout: Layout::DEFAULT,
+    };
+
+    /// Instagram: 40 (of 41) bits of milliseconds, 13 bits of shard, 10 bits of sequence
+    pub const INSTAGRAM: Self = Self {
+        name: "instagram",
+        epoch: 1314220021721,
+        layout: Layout::from_parts(40, 13, 10, FieldOrder::ShardSequence, 1),
+    };
+
+    /// Sonyflake: 39 bits of 10ms ticks, then 8 bits of sequence above 16 bits of machine ID
+    pub const SONYFLAKE: Self = Self {
+        name: "sonyflake",
+        epoch: 1409529600000,
+        layout: Layout::from_parts(39, 16, 8, FieldOrder::SequenceShard, 10),
+    };
+
+    /// Mastodon: 47 (of 48) bits of Unix milliseconds and 16 bits of sequence
+    pub const MASTODON: Self = Self {
+        name: "mastodon",
+        epoch: 0,
+        layout: Layout::from_parts(47, 0, 16, FieldOrder::ShardSequence, 1),
+    };
+
+    /// Every preset
+    pub const ALL: [Self; 5] = [
+        Self::TWITTER,
+        Self::DISCORD,
+        Self::INSTAGRAM,
+        Self::SONYFLAKE,
+        Self::MASTODON,
+    ];
+}
+
+impl fmt::Display for Preset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl FromStr for Preset {
+    type Err = Error;
+
+    /// Look up a preset by name, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.name.eq_ignore_ascii_case(s))
+            .ok_or_else(|| Error::UnknownPreset(s.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chronoflake, IdGenerator, MockClock};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn twitter_vector() {
+        // https://en.wikipedia.org/wiki/Snowflake_ID
+        let id = Chronoflake::new(1541815603606036480).with_preset(Preset::TWITTER);
+        assert_eq!(id.timestamp(), 1656432460105);
+        assert_eq!(id.datetime().to_rfc3339(), "2022-06-28T16:07:40.105+00:00");
+        assert_eq!(id.shard_id(), 378);
+        assert_eq!(id.sequence(), 0);
+    }
+
+    #[test]
+    fn discord_vector() {
+        // https://discord.com/developers/docs/reference#snowflakes
+        let id = Chronoflake::new(175928847299117063).with_preset(Preset::DISCORD);
+        assert_eq!(id.timestamp(), 1462015105796);
+        assert_eq!(id.shard_id() >> 5, 1); // worker
+        assert_eq!(id.shard_id() & 0x1F, 0); // process
+        assert_eq!(id.sequence(), 7);
+    }
+
+    #[test]
+    fn instagram_vector() {
+        // Worked example from
+        // https://instagram-engineering.com/sharding-ids-at-instagram-1cf5a71e5a5c:
+        // 1387263000ms into the epoch, shard 31341 % 2000, sequence 5001 % 1024
+        let id = Chronoflake::new(11637205501278089).with_preset(Preset::INSTAGRAM);
+        assert_eq!(id.timestamp() - Preset::INSTAGRAM.epoch, 1387263000);
+        assert_eq!(id.shard_id(), 1341);
+        assert_eq!(id.sequence(), 905);
+
+        // Media ID behind the post shortcode ybyPRoQWzX
+        let id = Chronoflake::new(908540701891980503).with_preset(Preset::INSTAGRAM);
+        assert_eq!(id.datetime().to_rfc3339(), "2015-01-29T10:15:13.321+00:00");
+        assert_eq!(id.shard_id(), 4187);
+        assert_eq!(id.sequence(), 215);
+    }
+
+    #[test]
+    fn sonyflake_round_trip() {
+        // Not a published ID: this only checks that IDs built from the documented
+        // layout (10ms ticks since 2014-09-01, the sequence above the machine ID)
+        // decode back to their parts, and that generated IDs take the same form
+        let epoch = Utc.with_ymd_and_hms(2014, 9, 1, 0, 0, 0).unwrap();
+        assert_eq!(Preset::SONYFLAKE.epoch, epoch.timestamp_millis() as u64);
+
+        let id = Chronoflake::new(1677967087).with_preset(Preset::SONYFLAKE);
+        assert_eq!(id.timestamp(), 1409529600000 + 1000);
+        assert_eq!(id.shard_id(), 0xBEEF);
+        assert_eq!(id.sequence(), 3);
+
+        let clock = MockClock::new(1409529600000 + 1234);
+        let mut cf = IdGenerator::new(0xBEEF)
+            .with_preset(Preset::SONYFLAKE)
+            .with_clock(clock);
+        assert_eq!(cf.generate_id(), Ok(2063646447));
+        assert_eq!(cf.generate_id(), Ok(2063711983));
+    }
+
+    #[test]
+    fn mastodon_vector() {
+        // Example status from https://docs.joinmastodon.org/entities/Status/,
+        // created at 2019-12-08T03:48:33Z
+        let id = Chronoflake::new(103270115826048975).with_preset(Preset::MASTODON);
+        assert_eq!(id.datetime().to_rfc3339(), "2019-12-08T03:48:33.849+00:00");
+        assert_eq!(id.shard_id(), 0);
+        assert_eq!(id.sequence(), 40911);
+    }
+
+    #[test]
+    fn parse_names() {
+        assert_eq!("Sonyflake".parse::<Preset>(), Ok(Preset::SONYFLAKE));
+        assert_eq!(Preset::DISCORD.to_string().parse(), Ok(Preset::DISCORD));
+        assert!("flickr".parse::<Preset>().is_err());
+    }
+}
@@ -0,0 +1,120 @@
+//! Export [`Metrics`] in the Prometheus text exposition format
+//!
+//! ```rust
+//! use chronoflake::IdGenerator;
+//!
+//! let mut cf = IdGenerator::new(14);
+//! cf.generate_id().unwrap();
+//!
+//! let shard = cf.shard_id.to_string();
+//! let text = chronoflake::prometheus::render(&cf.metrics, &[("shard", &shard)]);
+//! assert!(text.contains("chronoflake_ids_issued_total{shard=\"14\"} 1\n"));
+//! ```
+use std::fmt::Write;
+
+use crate::Metrics;
+
+/// Render a snapshot as Prometheus text, with the given labels on every sample
+///
+/// Serve the result with the content type `text/plain; version=0.0.4`.
+pub fn render(metrics: &Metrics, labels: &[(&str, &str)]) -> String {
+    let labels = format_labels(labels);
+    let samples = [
+        (
+            "chronoflake_ids_issued_total",
+            "counter",
+            "IDs issued by the generator",
+            metrics.ids_issued.to_string(),
+        ),
+        (
+            "chronoflake_sequence_exhaustions_total",
+            "counter",
+            "Times the sequence ran out and the generator waited for the next tick",
+            metrics.sequence_exhaustions.to_string(),
+        ),
+        (
+            "chronoflake_wait_seconds_total",
+            "counter",
+            "Time spent waiting for the next tick or for the clock to catch up",
+            metrics.wait_time.as_secs_f64().to_string(),
+        ),
+        (
+            "chronoflake_clock_rollbacks_total",
+            "counter",
+            "Times the clock read earlier than the reading before it",
+            metrics.clock_rollbacks.to_string(),
+        ),
+        (
+            "chronoflake_clock_max_rollback_seconds",
+            "gauge",
+            "Largest backwards jump of the clock",
+            metrics.max_rollback.as_secs_f64().to_string(),
+        ),
+    ];
+
+    let mut text = String::new();
+    for (name, kind, help, value) in samples {
+        let _ = writeln!(text, "# HELP {name} {help}");
+        let _ = writeln!(text, "# TYPE {name} {kind}");
+        let _ = writeln!(text, "{name}{labels} {value}");
+    }
+    text
+}
+
+fn format_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() {
+        return String::new();
+    }
+
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', r"\\")
+                .replace('"', r#"\""#)
+                .replace('\n', r"\n");
+            format!("{name}=\"{value}\"")
+        })
+        .collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{IdGenerator, MockClock, DEFAULT_EPOCH};
+
+    #[test]
+    fn renders_every_metric() {
+        let mut cf = IdGenerator::new(3).with_clock(MockClock::new(DEFAULT_EPOCH + 1000));
+        cf.generate_batch(5000).unwrap();
+
+        let text = render(&cf.metrics, &[]);
+        let samples: Vec<&str> = text.lines().filter(|l| !l.starts_with('#')).collect();
+        assert_eq!(
+            samples,
+            [
+                "chronoflake_ids_issued_total 5000",
+                "chronoflake_sequence_exhaustions_total 1",
+                &format!(
+                    "chronoflake_wait_seconds_total {}",
+                    cf.metrics.wait_time.as_secs_f64()
+                ),
+                "chronoflake_clock_rollbacks_total 0",
+                "chronoflake_clock_max_rollback_seconds 0",
+            ]
+        );
+        assert!(text.contains("# TYPE chronoflake_clock_max_rollback_seconds gauge\n"));
+    }
+
+    #[test]
+    fn escapes_labels() {
+        let text = render(
+            &Metrics::default(),
+            &[("shard", "7"), ("host", "a\"b\\c\nd")],
+        );
+        assert!(text.contains(
+            "chronoflake_clock_rollbacks_total{shard=\"7\",host=\"a\\\"b\\\\c\\nd\"} 0\n"
+        ));
+    }
+}
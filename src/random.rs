@@ -0,0 +1,41 @@
+//! Random bits for the 128-bit generators
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Non-cryptographic random numbers from the standard library's hasher seeds
+///
+/// Each value is the SipHash of a counter under keys picked randomly when the
+/// source is created, which is unpredictable enough to avoid collisions between
+/// generators but not to keep values secret.
+#[derive(Debug)]
+pub(crate) struct Random {
+    keys: RandomState,
+    counter: u64,
+}
+
+impl Random {
+    pub(crate) fn new() -> Self {
+        Self {
+            keys: RandomState::new(),
+            counter: 0,
+        }
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.counter = self.counter.wrapping_add(1);
+        let mut hasher = self.keys.build_hasher();
+        hasher.write_u64(self.counter);
+        hasher.finish()
+    }
+
+    pub(crate) fn next_u128(&mut self) -> u128 {
+        (self.next_u64() as u128) << 64 | self.next_u64() as u128
+    }
+}
+
+impl Clone for Random {
+    /// Start a new stream with fresh keys, so a clone never repeats the original's values
+    fn clone(&self) -> Self {
+        Self::new()
+    }
+}
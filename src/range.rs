@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::{Layout, Preset, DEFAULT_EPOCH};
+
+/// The IDs generated within a span of time
+///
+/// The timestamp is the most significant field of an ID, so every ID with a
+/// timestamp in `[start, end)` falls between [`min_id`](Self::min_id) and
+/// [`max_id`](Self::max_id). That turns "created between" queries into range
+/// scans on the primary key.
+///
+/// ```rust
+/// use chrono::{TimeZone, Utc};
+/// use chronoflake::IdRange;
+///
+/// let range = IdRange::between(
+///     Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+///     Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+/// )
+/// .with_epoch(1488432924251);
+///
+/// let (min, max) = (range.min_id().unwrap(), range.max_id().unwrap());
+/// let sql = format!("SELECT * FROM messages WHERE id BETWEEN {min} AND {max}");
+/// ```
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub struct IdRange {
+    start: u64,
+    end: u64,
+    epoch: u64,
+    layout: Layout,
+}
+
+impl IdRange {
+    /// IDs with a Unix timestamp (in milliseconds) from `start` up to but not including `end`,
+    /// generated with the default epoch and layout
+    pub fn new(start: u64, end: u64) -> Self {
+        Self {
+            start,
+            end,
+            epoch: DEFAULT_EPOCH,
+            layout: Layout::DEFAULT,
+        }
+    }
+
+    /// IDs generated from `start` up to but not including `end`
+    ///
+    /// Times before the Unix epoch are treated as the Unix epoch.
+    pub fn between(start: DateTime<Utc>, end: DateTime<Utc>) -> Self {
+        let millis = |datetime: DateTime<Utc>| datetime.timestamp_millis().max(0) as u64;
+        Self::new(millis(start), millis(end))
+    }
+
+    /// Set the epoch (in milliseconds) the IDs were generated with
+    pub fn with_epoch(mut self, epoch: u64) -> Self {
+        self.epoch = epoch;
+        self
+    }
+
+    /// Set the bit layout the IDs were generated with
+    pub fn with_layout(mut self, layout: Layout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Set the epoch and layout from a well-known ID scheme
+    pub fn with_preset(self, preset: Preset) -> Self {
+        self.with_epoch(preset.epoch).with_layout(preset.layout)
+    }
+
+    /// Start of the range as a Unix timestamp (in milliseconds), inclusive
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// End of the range as a Unix timestamp (in milliseconds), exclusive
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// The epoch (in milliseconds) the IDs are relative to
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// The bit layout of the IDs
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// Whether no ID can fall in the range
+    ///
+    /// This is the case when the range ends before it starts, lies before the
+    /// epoch or past the last timestamp the layout can hold, or is shorter than a
+    /// tick and does not contain the start of one.
+    pub fn is_empty(&self) -> bool {
+        let (start, end) = self.ticks();
+        start >= end
+    }
+
+    /// Smallest ID in the range, or `None` if it is empty
+    pub fn min_id(&self) -> Option<u64> {
+        let (start, end) = self.ticks();
+        (start < end).then(|| start << self.low_bits())
+    }
+
+    /// Largest ID in the range, or `None` if it is empty
+    pub fn max_id(&self) -> Option<u64> {
+        let (start, end) = self.ticks();
+        (start < end).then(|| (end << self.low_bits()) - 1)
+    }
+
+    /// Whether an ID was generated within the range
+    pub fn contains(&self, id: u64) -> bool {
+        match (self.min_id(), self.max_id()) {
+            (Some(min), Some(max)) => (min..=max).contains(&id),
+            _ => false,
+        }
+    }
+
+    /// Whether any ID could fall in both ranges
+    pub fn overlaps(&self, other: &IdRange) -> bool {
+        match (self.min_id(), self.max_id(), other.min_id(), other.max_id()) {
+            (Some(min), Some(max), Some(other_min), Some(other_max)) => {
+                min <= other_max && other_min <= max
+            }
+            _ => false,
+        }
+    }
+
+    /// Split the range into consecutive spans of time, the last of which may be shorter
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use chronoflake::IdRange;
+    ///
+    /// let day = IdRange::new(1704067200000, 1704153600000);
+    /// let hours: Vec<_> = day.buckets(Duration::from_secs(3600)).collect();
+    /// assert_eq!(hours.len(), 24);
+    /// assert_eq!(hours[0].min_id(), day.min_id());
+    /// assert_eq!(hours[23].max_id(), day.max_id());
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is shorter than a millisecond
+    pub fn buckets(&self, size: Duration) -> Buckets {
+        let size = size.as_millis() as u64;
+        assert!(size > 0, "bucket size must be at least 1ms");
+
+        Buckets {
+            range: *self,
+            next: self.start,
+            size,
+        }
+    }
+
+    /// Half-open range of ticks since the epoch, clamped to what the layout can hold
+    fn ticks(&self) -> (u64, u64) {
+        let limit = self.layout.max_timestamp() + 1;
+        let ticks = |millis: u64| {
+            let since_epoch = millis.saturating_sub(self.epoch);
+            since_epoch.div_ceil(self.layout.tick_millis()).min(limit)
+        };
+        (ticks(self.start), ticks(self.end))
+    }
+
+    fn low_bits(&self) -> u8 {
+        self.layout.shard_bits() + self.layout.sequence_bits()
+    }
+}
+
+/// Iterator over consecutive spans of an [`IdRange`], see [`IdRange::buckets`]
+#[derive(Clone, Debug)]
+pub struct Buckets {
+    range: IdRange,
+    next: u64,
+    size: u64,
+}
+
+impl Iterator for Buckets {
+    type Item = IdRange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.range.end {
+            return None;
+        }
+
+        let start = self.next;
+        self.next = start.saturating_add(self.size).min(self.range.end);
+        Some(IdRange {
+            start,
+            end: self.next,
+            ..self.range
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Chronoflake, IdGenerator, MockClock};
+    use chrono::TimeZone;
+
+    #[test]
+    fn bounds_match_shifted_timestamps() {
+        let range = IdRange::new(DEFAULT_EPOCH + 1000, DEFAULT_EPOCH + 2000);
+        assert_eq!(range.min_id(), Some(1000 << 22));
+        assert_eq!(range.max_id(), Some((2000 << 22) - 1));
+
+        assert!(range.contains(1000 << 22));
+        assert!(range.contains((1999 << 22) | (1023 << 12) | 4095));
+        assert!(!range.contains((1000 << 22) - 1));
+        assert!(!range.contains(2000 << 22));
+
+        let range = IdRange::between(
+            Utc.timestamp_millis_opt(1000).unwrap(),
+            Utc.timestamp_millis_opt(1001).unwrap(),
+        )
+        .with_epoch(0)
+        .with_layout(Layout::new(39, 14, 10).unwrap());
+        assert_eq!(range.min_id(), Some(1000 << 24));
+        assert_eq!(range.max_id(), Some((1001 << 24) - 1));
+    }
+
+    #[test]
+    fn contains_generated_ids() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 5000);
+        let mut cf = IdGenerator::new(1023).with_clock(clock.clone());
+        let ids = cf.generate_batch(10_000).unwrap();
+        let last = Chronoflake::new(ids[9_999]).timestamp();
+
+        let range = IdRange::new(DEFAULT_EPOCH + 5000, last + 1);
+        assert!(ids.iter().all(|&id| range.contains(id)));
+        assert!(!IdRange::new(DEFAULT_EPOCH + 5000, last).contains(ids[9_999]));
+        assert!(!IdRange::new(DEFAULT_EPOCH + 5001, last + 1).contains(ids[0]));
+    }
+
+    #[test]
+    fn empty_and_clamped_ranges() {
+        assert!(IdRange::new(DEFAULT_EPOCH + 10, DEFAULT_EPOCH + 10).is_empty());
+        assert!(IdRange::new(DEFAULT_EPOCH + 10, DEFAULT_EPOCH).is_empty());
+        assert!(IdRange::new(0, DEFAULT_EPOCH).is_empty());
+        assert_eq!(IdRange::new(0, DEFAULT_EPOCH + 1).min_id(), Some(0));
+        assert!(!IdRange::new(0, DEFAULT_EPOCH).contains(0));
+
+        let range = IdRange::new(DEFAULT_EPOCH, u64::MAX);
+        assert_eq!(range.max_id(), Some(i64::MAX as u64));
+
+        // IDs only carry the start of each 10ms tick
+        let layout = Layout::DEFAULT
+            .with_tick(Duration::from_millis(10))
+            .unwrap();
+        let range = IdRange::new(DEFAULT_EPOCH + 1, DEFAULT_EPOCH + 10).with_layout(layout);
+        assert!(range.is_empty());
+        let range = IdRange::new(DEFAULT_EPOCH + 1, DEFAULT_EPOCH + 11).with_layout(layout);
+        assert_eq!(range.min_id(), Some(1 << 22));
+        assert_eq!(range.max_id(), Some((2 << 22) - 1));
+    }
+
+    #[test]
+    fn overlapping_ranges() {
+        let range = IdRange::new(DEFAULT_EPOCH + 1000, DEFAULT_EPOCH + 2000);
+        assert!(range.overlaps(&IdRange::new(DEFAULT_EPOCH + 1999, DEFAULT_EPOCH + 3000)));
+        assert!(range.overlaps(&IdRange::new(DEFAULT_EPOCH, DEFAULT_EPOCH + 1001)));
+        assert!(!range.overlaps(&IdRange::new(DEFAULT_EPOCH + 2000, DEFAULT_EPOCH + 3000)));
+        assert!(!range.overlaps(&IdRange::new(DEFAULT_EPOCH + 1500, DEFAULT_EPOCH + 1500)));
+    }
+
+    #[test]
+    fn bucket_iteration() {
+        let range = IdRange::new(DEFAULT_EPOCH, DEFAULT_EPOCH + 25_000);
+        let buckets: Vec<_> = range.buckets(Duration::from_secs(10)).collect();
+        assert_eq!(buckets.len(), 3);
+        assert_eq!(buckets[2].start(), DEFAULT_EPOCH + 20_000);
+        assert_eq!(buckets[2].end(), DEFAULT_EPOCH + 25_000);
+
+        // Buckets cover the range without gaps or overlaps
+        assert_eq!(buckets[0].min_id(), range.min_id());
+        assert_eq!(buckets[2].max_id(), range.max_id());
+        for pair in buckets.windows(2) {
+            assert_eq!(pair[0].max_id().unwrap() + 1, pair[1].min_id().unwrap());
+            assert!(!pair[0].overlaps(&pair[1]));
+        }
+
+        assert_eq!(
+            IdRange::new(10, 10).buckets(Duration::from_secs(1)).count(),
+            0
+        );
+    }
+}
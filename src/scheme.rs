@@ -0,0 +1,203 @@
+use std::fmt;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::{
+    uuid, AtomicIdGenerator, Clock, Encoding, Error, IdGenerator, Ksuid, KsuidGenerator, Ulid,
+    UlidGenerator, UuidV7Generator,
+};
+
+/// Anything that generates time-ordered IDs
+///
+/// Implemented by the crate's blocking generators of new IDs so application code
+/// can be written once and handed whichever scheme a deployment uses. Each scheme
+/// has its own ID type and text form:
+///
+/// | Generator | ID | Text |
+/// |---|---|---|
+/// | [`IdGenerator`], [`AtomicIdGenerator`] | `u64` | decimal |
+/// | [`UuidV7Generator`] | `u128` | hyphenated hex |
+/// | [`UlidGenerator`] | [`Ulid`] | Crockford's base32 |
+/// | [`KsuidGenerator`] | [`Ksuid`] | base62 |
+///
+/// ```rust
+/// use chronoflake::{IdGenerator, IdScheme, UlidGenerator};
+///
+/// fn new_order<S: IdScheme>(ids: &mut S) -> String {
+///     let id = ids.generate_id().unwrap();
+///     ids.encode(id)
+/// }
+///
+/// let snowflake = new_order(&mut IdGenerator::new(14));
+/// let ulid = new_order(&mut UlidGenerator::new());
+/// ```
+pub trait IdScheme {
+    /// The IDs produced by the scheme, which sort in the order they were generated
+    type Id: Copy + Ord + fmt::Debug;
+
+    /// Generate a new ID, waiting if the scheme has run out of IDs for the current time
+    fn generate_id(&mut self) -> Result<Self::Id, Error>;
+
+    /// Unix timestamp (in milliseconds) at which an ID was generated
+    fn timestamp(&self, id: Self::Id) -> u64;
+
+    /// Time at which an ID was generated
+    ///
+    /// # Panics
+    ///
+    /// Panics if the timestamp is outside the range supported by `chrono`
+    fn datetime(&self, id: Self::Id) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(self.timestamp(id) as i64)
+            .single()
+            .expect("timestamp out of range")
+    }
+
+    /// Write an ID in the scheme's text form
+    fn encode(&self, id: Self::Id) -> String;
+
+    /// Parse an ID from the scheme's text form
+    fn parse(&self, encoded: &str) -> Result<Self::Id, Error>;
+}
+
+impl<C: Clock> IdScheme for IdGenerator<C> {
+    type Id = u64;
+
+    fn generate_id(&mut self) -> Result<u64, Error> {
+        IdGenerator::generate_id(self)
+    }
+
+    fn timestamp(&self, id: u64) -> u64 {
+        self.decode(id).timestamp()
+    }
+
+    fn encode(&self, id: u64) -> String {
+        id.to_string()
+    }
+
+    fn parse(&self, encoded: &str) -> Result<u64, Error> {
+        Encoding::Decimal.decode(encoded)
+    }
+}
+
+impl<C: Clock> IdScheme for AtomicIdGenerator<C> {
+    type Id = u64;
+
+    fn generate_id(&mut self) -> Result<u64, Error> {
+        AtomicIdGenerator::generate_id(self)
+    }
+
+    fn timestamp(&self, id: u64) -> u64 {
+        self.decode(id).timestamp()
+    }
+
+    fn encode(&self, id: u64) -> String {
+        id.to_string()
+    }
+
+    fn parse(&self, encoded: &str) -> Result<u64, Error> {
+        Encoding::Decimal.decode(encoded)
+    }
+}
+
+impl<C: Clock> IdScheme for UuidV7Generator<C> {
+    type Id = u128;
+
+    fn generate_id(&mut self) -> Result<u128, Error> {
+        UuidV7Generator::generate_id(self)
+    }
+
+    fn timestamp(&self, id: u128) -> u64 {
+        (id >> 80) as u64
+    }
+
+    fn encode(&self, id: u128) -> String {
+        uuid::hyphenated(id)
+    }
+
+    fn parse(&self, encoded: &str) -> Result<u128, Error> {
+        uuid::parse_hyphenated(encoded)
+    }
+}
+
+impl<C: Clock> IdScheme for UlidGenerator<C> {
+    type Id = Ulid;
+
+    fn generate_id(&mut self) -> Result<Ulid, Error> {
+        UlidGenerator::generate_id(self)
+    }
+
+    fn timestamp(&self, id: Ulid) -> u64 {
+        id.timestamp()
+    }
+
+    fn encode(&self, id: Ulid) -> String {
+        id.to_string()
+    }
+
+    fn parse(&self, encoded: &str) -> Result<Ulid, Error> {
+        encoded.parse()
+    }
+}
+
+impl<C: Clock> IdScheme for KsuidGenerator<C> {
+    type Id = Ksuid;
+
+    fn generate_id(&mut self) -> Result<Ksuid, Error> {
+        KsuidGenerator::generate_id(self)
+    }
+
+    fn timestamp(&self, id: Ksuid) -> u64 {
+        id.timestamp()
+    }
+
+    fn encode(&self, id: Ksuid) -> String {
+        id.to_string()
+    }
+
+    fn parse(&self, encoded: &str) -> Result<Ksuid, Error> {
+        encoded.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockClock, DEFAULT_EPOCH};
+
+    const NOW: u64 = DEFAULT_EPOCH + 500_000_000_000;
+
+    /// Behaviour every scheme should share, given a generator reading [`NOW`]
+    fn check_scheme<S: IdScheme>(mut scheme: S, resolution: u64) {
+        let ids: Vec<S::Id> = (0..100).map(|_| scheme.generate_id().unwrap()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+        for &id in &ids {
+            assert_eq!(scheme.timestamp(id), NOW - NOW % resolution);
+            assert_eq!(scheme.parse(&scheme.encode(id)), Ok(id));
+        }
+
+        let encoded: Vec<String> = ids.iter().map(|&id| scheme.encode(id)).collect();
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+        assert!(scheme.parse("").is_err());
+    }
+
+    #[test]
+    fn every_scheme() {
+        let clock = MockClock::new(NOW);
+        check_scheme(IdGenerator::new(1).with_clock(clock.clone()), 1);
+        check_scheme(
+            AtomicIdGenerator::from(IdGenerator::new(1).with_clock(clock.clone())),
+            1,
+        );
+        check_scheme(UuidV7Generator::new().with_clock(clock.clone()), 1);
+        check_scheme(UlidGenerator::new().with_clock(clock.clone()), 1);
+        check_scheme(KsuidGenerator::new().with_clock(clock.clone()), 1000);
+    }
+
+    #[test]
+    fn datetime_from_timestamp() {
+        let mut cf = IdGenerator::new(1).with_clock(MockClock::new(1704067200000));
+        let id = IdScheme::generate_id(&mut cf).unwrap();
+        assert_eq!(cf.datetime(id).to_rfc3339(), "2024-01-01T00:00:00+00:00");
+    }
+}
@@ -0,0 +1,177 @@
+//! Helpers for serializing IDs with [`serde`](::serde)
+//!
+//! JavaScript numbers lose precision above 2^53, so IDs sent to browsers are
+//! usually encoded as strings. Use [`string`] with `#[serde(with = "...")]` on
+//! `u64` or [`Chronoflake`](crate::Chronoflake) fields to write them as decimal
+//! strings while still accepting either numbers or strings when reading.
+//!
+//! ```rust
+//! use chronoflake::Chronoflake;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Message {
+//!     #[serde(with = "chronoflake::serde::string")]
+//!     id: u64,
+//!
+//!     #[serde(with = "chronoflake::serde::string::option")]
+//!     reply_to: Option<Chronoflake>,
+//! }
+//! ```
+use std::fmt;
+use std::marker::PhantomData;
+
+use ::serde::de::{self, Visitor};
+
+/// Serialize IDs as decimal strings, deserialize them from strings or numbers
+pub mod string {
+    use ::serde::{Deserializer, Serializer};
+
+    use super::IdVisitor;
+
+    /// Serialize an ID as a decimal string
+    pub fn serialize<S, T>(id: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Copy + Into<u64>,
+    {
+        serializer.collect_str(&(*id).into())
+    }
+
+    /// Deserialize an ID from a decimal string or a number
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: From<u64>,
+    {
+        deserializer.deserialize_any(IdVisitor::default())
+    }
+
+    /// The same as [`string`](super::string) for optional IDs
+    pub mod option {
+        use ::serde::{Deserialize, Deserializer, Serializer};
+
+        /// Serialize an ID as a decimal string, or `None` as null
+        pub fn serialize<S, T>(id: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+            T: Copy + Into<u64>,
+        {
+            match id {
+                Some(id) => super::serialize(id, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        /// Deserialize an ID from a decimal string, a number or null
+        pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+        where
+            D: Deserializer<'de>,
+            T: From<u64>,
+        {
+            #[derive(Deserialize)]
+            struct Id(#[serde(with = "super")] u64);
+
+            let id = Option::<Id>::deserialize(deserializer)?;
+            Ok(id.map(|Id(id)| id.into()))
+        }
+    }
+}
+
+struct IdVisitor<T>(PhantomData<T>);
+
+impl<T> Default for IdVisitor<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: From<u64>> Visitor<'_> for IdVisitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an ID as an unsigned integer or decimal string")
+    }
+
+    fn visit_u64<E: de::Error>(self, id: u64) -> Result<T, E> {
+        Ok(id.into())
+    }
+
+    fn visit_i64<E: de::Error>(self, id: i64) -> Result<T, E> {
+        u64::try_from(id)
+            .map(Into::into)
+            .map_err(|_| E::invalid_value(de::Unexpected::Signed(id), &self))
+    }
+
+    fn visit_str<E: de::Error>(self, id: &str) -> Result<T, E> {
+        id.parse::<u64>()
+            .map(Into::into)
+            .map_err(|_| E::invalid_value(de::Unexpected::Str(id), &self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::serde::{Deserialize, Serialize};
+
+    use crate::{Chronoflake, IdGenerator, Layout};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        #[serde(with = "crate::serde::string")]
+        id: u64,
+
+        #[serde(with = "crate::serde::string::option")]
+        reply_to: Option<Chronoflake>,
+    }
+
+    #[test]
+    fn ids_as_strings() {
+        let message = Message {
+            id: 1704967240656416804,
+            reply_to: Some(Chronoflake::new(1704967240656416803)),
+        };
+
+        let json = serde_json::to_string(&message).unwrap();
+        assert_eq!(
+            json,
+            r#"{"id":"1704967240656416804","reply_to":"1704967240656416803"}"#
+        );
+        assert_eq!(serde_json::from_str::<Message>(&json).unwrap(), message);
+
+        let json = r#"{"id":1704967240656416804,"reply_to":null}"#;
+        let message: Message = serde_json::from_str(json).unwrap();
+        assert_eq!(message.id, 1704967240656416804);
+        assert_eq!(message.reply_to, None);
+
+        assert!(serde_json::from_str::<Message>(r#"{"id":-1,"reply_to":null}"#).is_err());
+        assert!(serde_json::from_str::<Message>(r#"{"id":"abc","reply_to":null}"#).is_err());
+    }
+
+    #[test]
+    fn chronoflake_accepts_numbers_and_strings() {
+        let id = Chronoflake::new(1704967240656416804);
+        assert_eq!(serde_json::to_string(&id).unwrap(), "1704967240656416804");
+        assert_eq!(
+            serde_json::from_str::<Chronoflake>("1704967240656416804").unwrap(),
+            id
+        );
+        assert_eq!(
+            serde_json::from_str::<Chronoflake>(r#""1704967240656416804""#).unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn generator_state_round_trip() {
+        let mut cf = IdGenerator::new(49).with_layout(Layout::new(39, 14, 10).unwrap());
+        cf.generate_id().unwrap();
+
+        let json = serde_json::to_string(&cf).unwrap();
+        let restored: IdGenerator = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, cf);
+
+        let json = json.replace(r#""sequence_bits":10"#, r#""sequence_bits":11"#);
+        assert!(serde_json::from_str::<IdGenerator>(&json).is_err());
+    }
+}
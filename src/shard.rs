@@ -0,0 +1,327 @@
+use std::fmt;
+use std::fs;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::str::FromStr;
+
+use crate::{Error, Layout};
+
+/// Environment variable holding the hostname, set in every Kubernetes pod
+const HOSTNAME_VAR: &str = "HOSTNAME";
+
+/// Files that may hold the machine ID, in order of preference
+const MACHINE_ID_FILES: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// Where to find the shard ID when it is not given explicitly
+///
+/// Each source is checked against the layout's shard width when the generator is
+/// built, failing with [`Error::ShardIdTooLarge`] if the shard ID does not fit
+/// rather than risk two hosts sharing a shard. The only exception is the opt-in
+/// [`MachineIdHashed`](Self::MachineIdHashed).
+///
+/// ```rust
+/// use chronoflake::{IdGenerator, ShardSource};
+///
+/// std::env::set_var("SHARD_ID", "7");
+/// let cf = IdGenerator::builder()
+///     .with_shard_source(ShardSource::env("SHARD_ID"))
+///     .build()
+///     .unwrap();
+/// assert_eq!(cf.shard_id, 7);
+/// ```
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum ShardSource {
+    /// Parse the shard ID from the named environment variable
+    Env(String),
+
+    /// Use the trailing ordinal of a StatefulSet pod's hostname, e.g. `7` for `app-7`
+    HostnameOrdinal,
+
+    /// Use the low 16 bits of the host's private IPv4 address, as Sonyflake does
+    ///
+    /// Fails if those bits don't fit the layout, e.g. for `10.0.5.5` with 10 bits
+    /// of shard, since keeping fewer bits would give `10.0.1.5` the same shard.
+    PrivateIpv4,
+
+    /// Use a 16-bit FNV-1a hash of the machine ID in `/etc/machine-id`
+    ///
+    /// Fails if the hash doesn't fit the layout, so it is only useful with 16 bits
+    /// of shard. Different machine IDs can still hash to the same shard.
+    MachineId,
+
+    /// Like [`MachineId`](Self::MachineId), but keep as many low bits of the hash
+    /// as the layout has room for
+    ///
+    /// Never fails to fit, at the cost of a greater chance that two hosts end up
+    /// with the same shard the fewer shard bits there are.
+    MachineIdHashed,
+}
+
+impl ShardSource {
+    /// Read the shard ID from the named environment variable
+    pub fn env(name: impl Into<String>) -> Self {
+        Self::Env(name.into())
+    }
+
+    /// Look up the shard ID, checking that it fits the layout
+    pub fn resolve(&self, layout: &Layout) -> Result<u16, Error> {
+        match self {
+            Self::Env(name) => {
+                let value = std::env::var(name)
+                    .map_err(|e| Error::ShardUnavailable(format!("${name}: {e}")))?;
+                let shard_id = value.trim().parse().map_err(|_| {
+                    Error::ShardUnavailable(format!("${name}={value:?} is not a valid shard ID"))
+                })?;
+                check(shard_id, layout)
+            }
+            Self::HostnameOrdinal => {
+                let hostname = hostname()?;
+                let shard_id = ordinal(&hostname).ok_or_else(|| {
+                    Error::ShardUnavailable(format!("hostname {hostname:?} has no ordinal"))
+                })?;
+                check(shard_id, layout)
+            }
+            Self::PrivateIpv4 => {
+                let addr = private_ipv4()?;
+                check(ipv4_shard(addr), layout)
+            }
+            Self::MachineId => check(hash_shard(&machine_id()?), layout),
+            Self::MachineIdHashed => Ok(hash_shard(&machine_id()?) & layout.max_shard_id()),
+        }
+    }
+}
+
+impl fmt::Display for ShardSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Env(name) => write!(f, "env:{name}"),
+            Self::HostnameOrdinal => write!(f, "hostname"),
+            Self::PrivateIpv4 => write!(f, "ipv4"),
+            Self::MachineId => write!(f, "machine-id"),
+            Self::MachineIdHashed => write!(f, "machine-id-hashed"),
+        }
+    }
+}
+
+impl FromStr for ShardSource {
+    type Err = Error;
+
+    /// Parse a source written as `env:NAME`, `hostname`, `ipv4`, `machine-id` or
+    /// `machine-id-hashed`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "hostname" => Ok(Self::HostnameOrdinal),
+            "ipv4" => Ok(Self::PrivateIpv4),
+            "machine-id" => Ok(Self::MachineId),
+            "machine-id-hashed" => Ok(Self::MachineIdHashed),
+            _ => match s.strip_prefix("env:") {
+                Some(name) if !name.is_empty() => Ok(Self::env(name)),
+                _ => Err(Error::ShardUnavailable(format!(
+                    "expected env:NAME, hostname, ipv4, machine-id or machine-id-hashed, got {s:?}"
+                ))),
+            },
+        }
+    }
+}
+
+fn check(shard_id: u16, layout: &Layout) -> Result<u16, Error> {
+    let max = layout.max_shard_id();
+    if shard_id > max {
+        return Err(Error::ShardIdTooLarge { shard_id, max });
+    }
+
+    Ok(shard_id)
+}
+
+fn hostname() -> Result<String, Error> {
+    std::env::var(HOSTNAME_VAR)
+        .ok()
+        .or_else(|| fs::read_to_string("/proc/sys/kernel/hostname").ok())
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| Error::ShardUnavailable("could not determine the hostname".to_string()))
+}
+
+/// Trailing number of a hostname after its last `-`
+fn ordinal(hostname: &str) -> Option<u16> {
+    let (_, ordinal) = hostname.rsplit_once('-')?;
+    ordinal.parse().ok()
+}
+
+/// Address of the interface used for outbound traffic, which must be private
+fn private_ipv4() -> Result<Ipv4Addr, Error> {
+    let unavailable = |e: std::io::Error| Error::ShardUnavailable(format!("private IPv4: {e}"));
+
+    // Connecting a UDP socket picks a route without sending anything
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).map_err(unavailable)?;
+    socket
+        .connect((Ipv4Addr::new(192, 0, 2, 1), 9))
+        .map_err(unavailable)?;
+    match socket.local_addr().map_err(unavailable)?.ip() {
+        std::net::IpAddr::V4(addr) if addr.is_private() => Ok(addr),
+        addr => Err(Error::ShardUnavailable(format!(
+            "{addr} is not a private IPv4 address"
+        ))),
+    }
+}
+
+fn ipv4_shard(addr: Ipv4Addr) -> u16 {
+    u32::from(addr) as u16
+}
+
+fn machine_id() -> Result<String, Error> {
+    MACHINE_ID_FILES
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| Error::ShardUnavailable("no machine ID found".to_string()))
+}
+
+fn hash_shard(machine_id: &str) -> u16 {
+    let hash = machine_id
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    hash as u16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_source() {
+        let layout = Layout::DEFAULT;
+        std::env::set_var("CHRONOFLAKE_TEST_SHARD", " 42\n");
+        assert_eq!(
+            ShardSource::env("CHRONOFLAKE_TEST_SHARD").resolve(&layout),
+            Ok(42)
+        );
+
+        std::env::set_var("CHRONOFLAKE_TEST_SHARD_LARGE", "1024");
+        assert_eq!(
+            ShardSource::env("CHRONOFLAKE_TEST_SHARD_LARGE").resolve(&layout),
+            Err(Error::ShardIdTooLarge {
+                shard_id: 1024,
+                max: 1023
+            })
+        );
+
+        std::env::set_var("CHRONOFLAKE_TEST_SHARD_BAD", "one");
+        assert!(ShardSource::env("CHRONOFLAKE_TEST_SHARD_BAD")
+            .resolve(&layout)
+            .is_err());
+        assert!(ShardSource::env("CHRONOFLAKE_TEST_SHARD_UNSET")
+            .resolve(&layout)
+            .is_err());
+    }
+
+    #[test]
+    fn hostname_ordinals() {
+        assert_eq!(ordinal("app-7"), Some(7));
+        assert_eq!(ordinal("id-service-12"), Some(12));
+        assert_eq!(ordinal("app"), None);
+        assert_eq!(ordinal("app-"), None);
+        assert_eq!(ordinal("app-7a"), None);
+        assert_eq!(ordinal("app-70000"), None);
+    }
+
+    #[test]
+    fn ipv4_low_bits() {
+        let addr = Ipv4Addr::new(10, 0, 190, 239);
+        assert_eq!(ipv4_shard(addr), 0xBEEF);
+        assert_eq!(
+            check(ipv4_shard(addr), &crate::Preset::SONYFLAKE.layout),
+            Ok(0xBEEF)
+        );
+        assert_eq!(
+            check(ipv4_shard(addr), &Layout::new(47, 0, 16).unwrap()),
+            Err(Error::ShardIdTooLarge {
+                shard_id: 0xBEEF,
+                max: 0
+            })
+        );
+    }
+
+    #[test]
+    fn ipv4_never_truncated() {
+        // Masked to 10 bits, both addresses would be shard 0x105
+        let layout = Layout::DEFAULT;
+        assert_eq!(
+            check(ipv4_shard(Ipv4Addr::new(10, 0, 1, 5)), &layout),
+            Ok(0x105)
+        );
+        assert_eq!(
+            check(ipv4_shard(Ipv4Addr::new(10, 0, 5, 5)), &layout),
+            Err(Error::ShardIdTooLarge {
+                shard_id: 0x505,
+                max: 1023
+            })
+        );
+    }
+
+    #[test]
+    fn machine_id_hash() {
+        let id = "b08dfa6083e7567a1921a715000001fb";
+        assert_eq!(hash_shard(id), hash_shard(id));
+        assert_ne!(
+            hash_shard(id),
+            hash_shard("b08dfa6083e7567a1921a715000001fc")
+        );
+
+        // FNV-1a test vector: "a" hashes to 0xaf63dc4c8601ec8c
+        assert_eq!(hash_shard("a"), 0xec8c);
+    }
+
+    #[test]
+    fn machine_id_must_fit() {
+        let Ok(machine_id) = machine_id() else {
+            return;
+        };
+        let hash = hash_shard(&machine_id);
+
+        let wide = Layout::new(31, 16, 16).unwrap();
+        assert_eq!(ShardSource::MachineId.resolve(&wide), Ok(hash));
+        assert_eq!(ShardSource::MachineIdHashed.resolve(&wide), Ok(hash));
+
+        let narrow = Layout::new(62, 1, 0).unwrap();
+        let reduced = ShardSource::MachineIdHashed.resolve(&narrow);
+        assert_eq!(reduced, Ok(hash & 1));
+        if hash > 1 {
+            assert_eq!(
+                ShardSource::MachineId.resolve(&narrow),
+                Err(Error::ShardIdTooLarge {
+                    shard_id: hash,
+                    max: 1
+                })
+            );
+        }
+
+        // The test vector's hash only fits 16 bits of shard
+        assert_eq!(
+            check(hash_shard("a"), &Layout::DEFAULT),
+            Err(Error::ShardIdTooLarge {
+                shard_id: 0xec8c,
+                max: 1023
+            })
+        );
+    }
+
+    #[test]
+    fn parse_and_display() {
+        for source in [
+            ShardSource::env("SHARD_ID"),
+            ShardSource::HostnameOrdinal,
+            ShardSource::PrivateIpv4,
+            ShardSource::MachineId,
+            ShardSource::MachineIdHashed,
+        ] {
+            assert_eq!(source.to_string().parse::<ShardSource>(), Ok(source));
+        }
+
+        assert!("env:".parse::<ShardSource>().is_err());
+        assert!("dns".parse::<ShardSource>().is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! Timestamp and sequence state machine shared by the generators
+use std::time::Duration;
+
+use crate::{Clock, Error, Layout, RollbackPolicy, WaitStrategy};
+
+/// Work out the timestamp and sequence of the next ID from the last issued ones
+/// and the current clock reading `now`
+pub(crate) fn advance(
+    layout: &Layout,
+    rollback_policy: RollbackPolicy,
+    epoch: u64,
+    timestamp: u64,
+    sequence: u16,
+    now: u64,
+) -> Result<(u64, u16), Error> {
+    if now < epoch {
+        return Err(Error::ClockBeforeEpoch);
+    }
+
+    // Round down to the start of the current tick
+    let now = epoch + layout.to_millis(layout.to_ticks(now - epoch));
+    let (timestamp, sequence) = next(layout, rollback_policy, timestamp, sequence, now)?;
+    if layout.to_ticks(timestamp - epoch) > layout.max_timestamp() {
+        return Err(Error::TimestampOverflow {
+            max: layout.max_timestamp(),
+        });
+    }
+
+    Ok((timestamp, sequence))
+}
+
+/// Like [`advance`], but claim up to `count` consecutive sequence numbers within
+/// one tick, returning the timestamp and the first and last sequence claimed
+pub(crate) fn advance_by(
+    layout: &Layout,
+    rollback_policy: RollbackPolicy,
+    epoch: u64,
+    timestamp: u64,
+    sequence: u16,
+    now: u64,
+    count: usize,
+) -> Result<(u64, u16, u16), Error> {
+    let (timestamp, first) = advance(layout, rollback_policy, epoch, timestamp, sequence, now)?;
+    let spare = (layout.max_sequence() - first) as usize;
+    let last = first + spare.min(count.saturating_sub(1)) as u16;
+    Ok((timestamp, first, last))
+}
+
+/// Like [`advance`], for IDs ordered within a tick by a `bits` wide random value
+/// instead of a sequence
+///
+/// A new tick starts from the `fresh` random value and every later ID in the same
+/// tick increments the last one, failing with [`Error::SequenceExhausted`] once
+/// it would overflow.
+pub(crate) fn advance_random(
+    layout: &Layout,
+    rollback_policy: RollbackPolicy,
+    epoch: u64,
+    (timestamp, value): (u64, u128),
+    now: u64,
+    fresh: u128,
+    bits: u32,
+) -> Result<(u64, u128), Error> {
+    if now < epoch {
+        return Err(Error::ClockBeforeEpoch);
+    }
+
+    let now = epoch + layout.to_millis(layout.to_ticks(now - epoch));
+    if now > timestamp {
+        if layout.to_ticks(now - epoch) > layout.max_timestamp() {
+            return Err(Error::TimestampOverflow {
+                max: layout.max_timestamp(),
+            });
+        }
+        return Ok((now, fresh & (u128::MAX >> (128 - bits))));
+    }
+
+    if now < timestamp && rollback_policy != RollbackPolicy::Continue {
+        return Err(Error::ClockMovedBackwards {
+            by: timestamp - now,
+        });
+    }
+
+    match value.checked_add(1) {
+        Some(value) if bits == 128 || value >> bits == 0 => Ok((timestamp, value)),
+        _ => Err(Error::SequenceExhausted),
+    }
+}
+
+fn next(
+    layout: &Layout,
+    rollback_policy: RollbackPolicy,
+    timestamp: u64,
+    sequence: u16,
+    now: u64,
+) -> Result<(u64, u16), Error> {
+    if now > timestamp {
+        return Ok((now, 0));
+    }
+
+    let behind = now < timestamp;
+    if behind && rollback_policy != RollbackPolicy::Continue {
+        return Err(Error::ClockMovedBackwards {
+            by: timestamp - now,
+        });
+    }
+
+    if sequence < layout.max_sequence() {
+        Ok((timestamp, sequence + 1))
+    } else if behind {
+        // Already running ahead of the clock, so borrow the next tick
+        Ok((timestamp + layout.tick_millis(), 0))
+    } else {
+        Err(Error::SequenceExhausted)
+    }
+}
+
+/// Wait out a failed attempt at generating an ID so it can be retried
+///
+/// Sequence exhaustion and any clock rollback the policy allows are waited out,
+/// everything else is handed back to the caller. With ticks longer than 1ms an
+/// exhausted sequence sleeps until the next tick rather than polling the clock.
+pub(crate) fn recover(
+    clock: &impl Clock,
+    layout: &Layout,
+    epoch: u64,
+    wait_strategy: WaitStrategy,
+    rollback_policy: RollbackPolicy,
+    err: Error,
+) -> Result<(), Error> {
+    match err {
+        Error::SequenceExhausted if layout.tick_millis() == 1 => clock.wait(wait_strategy),
+        err => clock.sleep(backoff(clock, layout, epoch, rollback_policy, err)?),
+    }
+
+    Ok(())
+}
+
+/// How long to wait before retrying a failed attempt at generating an ID
+///
+/// An exhausted sequence waits for the next tick and a clock rollback the policy
+/// allows waits for the clock to catch up. Everything else is handed back.
+pub(crate) fn backoff(
+    clock: &impl Clock,
+    layout: &Layout,
+    epoch: u64,
+    rollback_policy: RollbackPolicy,
+    err: Error,
+) -> Result<Duration, Error> {
+    match err {
+        Error::SequenceExhausted => {
+            let into_tick = clock.now_millis().saturating_sub(epoch) % layout.tick_millis();
+            Ok(Duration::from_millis(layout.tick_millis() - into_tick))
+        }
+        Error::ClockMovedBackwards { by } => match rollback_policy {
+            RollbackPolicy::Wait(max) if Duration::from_millis(by) <= max => {
+                Ok(Duration::from_millis(by))
+            }
+            _ => Err(err),
+        },
+        _ => Err(err),
+    }
+}
@@ -0,0 +1,212 @@
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::Error;
+
+/// Default amount of time reserved ahead of the last issued ID on each write
+pub const DEFAULT_RESERVATION: Duration = Duration::from_secs(1);
+
+/// File recording a high-water timestamp so a restarted generator cannot reissue IDs
+///
+/// Rather than writing on every ID, the file reserves a window ahead of the clock
+/// and is only rewritten once IDs are issued past it. A generator restored from
+/// the file treats everything up to the high-water mark as already issued, so
+/// after a restart it waits for the clock to pass the mark. If the clock is
+/// behind by more than the reservation it has gone backwards, and the generator
+/// waits or fails according to its [`RollbackPolicy`].
+///
+/// [`RollbackPolicy`]: crate::RollbackPolicy
+///
+/// ```rust,no_run
+/// use chronoflake::{IdGenerator, StateFile};
+///
+/// let mut cf = IdGenerator::builder()
+///     .with_shard_id(16)
+///     .with_state_file(StateFile::new("/var/lib/myapp/chronoflake.state"))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StateFile {
+    path: PathBuf,
+    reservation: Duration,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    reserved: u64,
+}
+
+impl StateFile {
+    /// Use the file at the given path, which is created on first write
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            reservation: DEFAULT_RESERVATION,
+            reserved: 0,
+        }
+    }
+
+    /// Set how far ahead of the last issued ID each write reserves
+    ///
+    /// Longer reservations mean fewer writes but a longer wait after a restart.
+    pub fn with_reservation(mut self, reservation: Duration) -> Self {
+        self.reservation = reservation;
+        self
+    }
+
+    /// Path of the file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// How far ahead of the last issued ID each write reserves
+    pub fn reservation(&self) -> Duration {
+        self.reservation
+    }
+
+    /// High-water timestamp (in milliseconds) reserved so far
+    pub fn reserved(&self) -> u64 {
+        self.reserved
+    }
+
+    /// Read the high-water timestamp (in milliseconds), if the file exists
+    pub fn load(&mut self) -> Result<Option<u64>, Error> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(self.error(e)),
+        };
+
+        let high_water = contents
+            .trim()
+            .parse()
+            .map_err(|_| self.error(format!("invalid timestamp {:?}", contents.trim())))?;
+
+        self.reserved = self.reserved.max(high_water);
+        Ok(Some(high_water))
+    }
+
+    /// Make sure the file covers an ID issued at `timestamp`, extending the
+    /// reservation if it does not
+    pub(crate) fn reserve(&mut self, timestamp: u64) -> Result<(), Error> {
+        if timestamp <= self.reserved {
+            return Ok(());
+        }
+
+        let high_water = timestamp + self.reservation.as_millis() as u64;
+        self.write(high_water).map_err(|e| self.error(e))?;
+        self.reserved = high_water;
+        Ok(())
+    }
+
+    /// Atomically replace the file, syncing both the contents and the rename
+    fn write(&self, high_water: u64) -> std::io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        let mut file = File::create(&tmp)?;
+        writeln!(file, "{high_water}")?;
+        file.sync_all()?;
+        fs::rename(&tmp, &self.path)?;
+
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        // Directories can't be opened for syncing on every platform
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+
+        Ok(())
+    }
+
+    fn error(&self, reason: impl ToString) -> Error {
+        Error::StateFile {
+            path: self.path.clone(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempPath;
+    use crate::{Clock, IdGenerator, MockClock, RollbackPolicy, DEFAULT_EPOCH};
+
+    #[test]
+    fn reserves_ahead() {
+        let path = TempPath::new("reserves-ahead.state");
+        let mut file = StateFile::new(&*path).with_reservation(Duration::from_secs(5));
+        assert_eq!(file.load(), Ok(None));
+
+        file.reserve(1000).unwrap();
+        assert_eq!(file.reserved(), 6000);
+        fs::write(&path, "garbage").unwrap();
+        file.reserve(5000).unwrap();
+        assert!(StateFile::new(&*path).load().is_err());
+
+        file.reserve(7000).unwrap();
+        assert_eq!(StateFile::new(&*path).load(), Ok(Some(12000)));
+    }
+
+    #[test]
+    fn restart_cannot_reissue() {
+        let path = TempPath::new("restart.state");
+        let clock = MockClock::new(DEFAULT_EPOCH + 100_000);
+        let builder = IdGenerator::builder()
+            .with_shard_id(49)
+            .with_clock(clock.clone())
+            .with_state_file(StateFile::new(&*path));
+
+        let mut cf = builder.clone().build().unwrap();
+        let last = (0..10).map(|_| cf.generate_id().unwrap()).last().unwrap();
+        drop(cf);
+
+        // A restart after the clock stepped back must not go behind the last ID
+        clock.rewind(Duration::from_secs(30));
+        let err = builder
+            .clone()
+            .with_rollback_policy(RollbackPolicy::Error)
+            .build()
+            .unwrap_err();
+        assert!(matches!(err, Error::ClockMovedBackwards { by: 31_000 }));
+
+        let mut cf = builder
+            .clone()
+            .with_rollback_policy(RollbackPolicy::Continue)
+            .build()
+            .unwrap();
+        assert!(cf.generate_id().unwrap() > last);
+
+        // Waiting out the reservation moves the clock past the high-water mark
+        clock.advance(Duration::from_secs(32));
+        let before = clock.now_millis();
+        let mut cf = builder.build().unwrap();
+        assert!(clock.now_millis() > before);
+        assert!(cf.generate_id().unwrap() > last);
+    }
+
+    #[test]
+    fn quick_restart_waits_out_reservation() {
+        let path = TempPath::new("quick-restart.state");
+        let clock = MockClock::new(DEFAULT_EPOCH + 100_000);
+        let builder = IdGenerator::builder()
+            .with_shard_id(49)
+            .with_clock(clock.clone())
+            .with_state_file(StateFile::new(&*path).with_reservation(Duration::from_secs(10)));
+
+        let mut cf = builder.clone().build().unwrap();
+        let last = cf.generate_id().unwrap();
+        drop(cf);
+
+        // The mark is 10s ahead, further than the default policy's 1s, but that
+        // is the reservation rather than the clock going backwards
+        clock.advance(Duration::from_millis(200));
+        let mut cf = builder.build().unwrap();
+        assert_eq!(clock.now_millis(), DEFAULT_EPOCH + 110_000);
+        assert!(cf.generate_id().unwrap() > last);
+    }
+}
@@ -0,0 +1,42 @@
+//! Helpers shared by the unit tests
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// Path in the temporary directory, removed along with anything created there
+/// when dropped, including when a test fails
+#[derive(Debug)]
+pub(crate) struct TempPath(PathBuf);
+
+impl TempPath {
+    /// Path unique to the process and `name`, clearing out any left behind
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("chronoflake-{}-{name}", std::process::id()));
+        remove(&path);
+        Self(path)
+    }
+}
+
+impl Deref for TempPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempPath {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        remove(&self.0);
+    }
+}
+
+fn remove(path: &Path) {
+    let _ = fs::remove_dir_all(path).or_else(|_| fs::remove_file(path));
+}
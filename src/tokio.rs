@@ -0,0 +1,244 @@
+//! Async ID generation for tokio runtimes
+//!
+//! [`AsyncIdGenerator`] issues the same IDs as [`IdGenerator`] but sleeps on the
+//! tokio timer when the sequence is exhausted or the clock has gone backwards,
+//! rather than blocking the executor thread.
+//!
+//! ```rust
+//! use chronoflake::tokio::AsyncIdGenerator;
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let mut cf = AsyncIdGenerator::new(16);
+//! let id = cf.generate_id().await.unwrap();
+//! assert_eq!(cf.decode(id).shard_id(), 16);
+//! # }
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use ::tokio::time::{sleep, Sleep};
+use futures_core::Stream;
+
+use crate::{state, Chronoflake, Clock, Error, IdGenerator, SystemClock};
+
+/// Unique ID generator that waits asynchronously
+///
+/// Wraps an [`IdGenerator`] and shares its settings and state, so IDs from either
+/// are interchangeable. The wait strategy is not used: waits are always tokio
+/// sleeps until the next tick, or until the clock catches up when the rollback
+/// policy allows it.
+#[derive(Clone, Debug)]
+pub struct AsyncIdGenerator<C = SystemClock> {
+    inner: IdGenerator<C>,
+}
+
+impl AsyncIdGenerator {
+    /// Create a new async ID generator with the default settings
+    pub fn new(shard_id: u16) -> Self {
+        IdGenerator::new(shard_id).into()
+    }
+}
+
+impl<C: Clock> AsyncIdGenerator<C> {
+    /// Generate a unique ID, sleeping while the sequence is exhausted
+    ///
+    /// Fails in the same way as [`IdGenerator::generate_id`].
+    pub async fn generate_id(&mut self) -> Result<u64, Error> {
+        loop {
+            match self.inner.try_generate_id() {
+                Ok(id) => return Ok(id),
+                Err(err) => sleep(self.backoff(err)?).await,
+            }
+        }
+    }
+
+    /// Generate a unique ID without waiting
+    ///
+    /// Fails in the same way as [`IdGenerator::try_generate_id`].
+    pub fn try_generate_id(&mut self) -> Result<u64, Error> {
+        self.inner.try_generate_id()
+    }
+
+    /// Turn the generator into an endless [`Stream`] of IDs
+    ///
+    /// The stream ends after yielding the first error that cannot be waited out.
+    pub fn into_stream(self) -> IdStream<C> {
+        IdStream {
+            generator: self,
+            delay: None,
+            done: false,
+        }
+    }
+
+    /// Decode an ID generated with this generator's epoch and layout
+    pub fn decode(&self, id: u64) -> Chronoflake {
+        self.inner.decode(id)
+    }
+
+    /// The wrapped generator
+    pub fn get_ref(&self) -> &IdGenerator<C> {
+        &self.inner
+    }
+
+    /// Unwrap the generator, keeping its last issued ID
+    pub fn into_inner(self) -> IdGenerator<C> {
+        self.inner
+    }
+
+    /// How long to sleep before retrying, recorded in the metrics as time spent waiting
+    fn backoff(&mut self, err: Error) -> Result<std::time::Duration, Error> {
+        let cf = &mut self.inner;
+        let exhausted = err == Error::SequenceExhausted;
+        let delay = state::backoff(&cf.clock, &cf.layout, cf.epoch, cf.rollback_policy, err)?;
+        cf.metrics.record_wait(delay, exhausted);
+
+        Ok(delay)
+    }
+}
+
+impl<C: Clock> From<IdGenerator<C>> for AsyncIdGenerator<C> {
+    fn from(inner: IdGenerator<C>) -> Self {
+        Self { inner }
+    }
+}
+
+/// Endless stream of IDs from an [`AsyncIdGenerator`]
+///
+/// ```rust
+/// use std::pin::pin;
+/// use std::future::poll_fn;
+/// use futures_core::Stream;
+/// use chronoflake::tokio::AsyncIdGenerator;
+///
+/// # #[tokio::main(flavor = "current_thread")]
+/// # async fn main() {
+/// let mut ids = pin!(AsyncIdGenerator::new(16).into_stream());
+/// let first = poll_fn(|cx| ids.as_mut().poll_next(cx)).await.unwrap().unwrap();
+/// let second = poll_fn(|cx| ids.as_mut().poll_next(cx)).await.unwrap().unwrap();
+/// assert!(first < second);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct IdStream<C = SystemClock> {
+    generator: AsyncIdGenerator<C>,
+    delay: Option<Pin<Box<Sleep>>>,
+    done: bool,
+}
+
+impl<C> IdStream<C> {
+    /// Stop the stream and get the generator back
+    pub fn into_inner(self) -> AsyncIdGenerator<C> {
+        self.generator
+    }
+}
+
+impl<C: Clock + Unpin> Stream for IdStream<C> {
+    type Item = Result<u64, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+
+        loop {
+            if let Some(delay) = &mut this.delay {
+                if delay.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                this.delay = None;
+            }
+
+            let err = match this.generator.try_generate_id() {
+                Ok(id) => return Poll::Ready(Some(Ok(id))),
+                Err(err) => err,
+            };
+
+            match this.generator.backoff(err) {
+                Ok(duration) => this.delay = Some(Box::pin(sleep(duration))),
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::future::poll_fn;
+    use std::time::Duration;
+
+    use crate::{Layout, MockClock, RollbackPolicy, DEFAULT_EPOCH};
+
+    #[tokio::test]
+    async fn matches_id_generator_format() {
+        let layout = Layout::new(39, 14, 10).unwrap();
+        let cf = IdGenerator::new(9000)
+            .with_epoch(1488432924251)
+            .with_layout(layout);
+        let mut generator = AsyncIdGenerator::from(cf.clone());
+
+        // Enough IDs to run through the sequence several times
+        let count = 5 * (layout.max_sequence() as usize + 1);
+        let mut ids = Vec::with_capacity(count);
+        for _ in 0..count {
+            ids.push(generator.generate_id().await.unwrap());
+        }
+
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        let id = cf.decode(ids[0]);
+        assert_eq!(id, generator.decode(ids[0]));
+        assert_eq!(id.shard_id(), 9000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sleeps_until_clock_moves() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let mut cf = AsyncIdGenerator::from(IdGenerator::new(49).with_clock(clock.clone()));
+
+        while cf.try_generate_id().is_ok() {}
+
+        // Only move the clock once the generator has had to wait for it
+        let handle = clock.clone();
+        ::tokio::spawn(async move {
+            sleep(Duration::from_millis(5)).await;
+            handle.advance(Duration::from_millis(1));
+        });
+
+        let raw = cf.generate_id().await.unwrap();
+        let id = cf.decode(raw);
+        assert_eq!(id.timestamp(), DEFAULT_EPOCH + 1001);
+        assert_eq!(id.sequence(), 0);
+
+        clock.rewind(Duration::from_millis(10));
+        let mut cf =
+            AsyncIdGenerator::from(cf.into_inner().with_rollback_policy(RollbackPolicy::Error));
+        assert_eq!(
+            cf.generate_id().await,
+            Err(Error::ClockMovedBackwards { by: 10 })
+        );
+    }
+
+    #[tokio::test]
+    async fn stream_yields_unique_ids() {
+        let mut ids = AsyncIdGenerator::new(49).into_stream();
+        let mut seen = HashSet::new();
+        for _ in 0..10_000 {
+            let id = poll_fn(|cx| Pin::new(&mut ids).poll_next(cx)).await;
+            assert!(seen.insert(id.unwrap().unwrap()));
+        }
+
+        // The stream ends once it hits an error it cannot wait out
+        let clock = MockClock::new(DEFAULT_EPOCH - 1);
+        let mut ids = AsyncIdGenerator::from(IdGenerator::new(49).with_clock(clock)).into_stream();
+        let first = poll_fn(|cx| Pin::new(&mut ids).poll_next(cx)).await;
+        assert_eq!(first, Some(Err(Error::ClockBeforeEpoch)));
+        assert_eq!(poll_fn(|cx| Pin::new(&mut ids).poll_next(cx)).await, None);
+    }
+}
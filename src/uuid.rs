@@ -0,0 +1,371 @@
+use crate::layout::FieldOrder;
+use crate::random::Random;
+use crate::{state, Chronoflake, Clock, Error, Layout, RollbackPolicy, SystemClock, WaitStrategy};
+
+/// UUIDv7 fields laid out as a snowflake: 48 bits of Unix milliseconds and a
+/// 12 bit counter in `rand_a`, with no shard
+const UUID_LAYOUT: Layout = Layout::from_parts(48, 0, 12, FieldOrder::ShardSequence, 1);
+
+const VERSION: u128 = 0x7 << 76;
+const VARIANT: u128 = 0b10 << 62;
+const VERSION_MASK: u128 = 0xF << 76;
+const VARIANT_MASK: u128 = 0b11 << 62;
+const MAX_UNIX_MILLIS: u64 = (1 << 48) - 1;
+
+/// Bits of the ID left unused at the bottom of `rand_b` when embedded in a UUID
+const EMBED_PADDING: u32 = 10;
+
+/// UUIDv7 generator sharing the clock handling of [`IdGenerator`](crate::IdGenerator)
+///
+/// UUIDs hold the Unix timestamp in milliseconds, then a 12 bit counter in
+/// `rand_a` that orders UUIDs generated within the same millisecond (method 1 of
+/// RFC 9562), then 62 random bits. Exhausting the counter or the clock going
+/// backwards is handled with the same [`WaitStrategy`] and [`RollbackPolicy`]
+/// as the snowflake generators.
+///
+/// The random bits come from the standard library's hasher seeds and are not
+/// suitable where UUIDs must be unguessable.
+///
+/// ```rust
+/// use chronoflake::UuidV7Generator;
+///
+/// let mut generator = UuidV7Generator::new();
+/// let first = generator.generate_id().unwrap();
+/// let second = generator.generate_id().unwrap();
+/// assert!(first < second);
+/// assert_eq!((first >> 76) & 0xF, 7);
+/// ```
+#[derive(Clone, Debug)]
+pub struct UuidV7Generator<C = SystemClock> {
+    /// Counter of the last issued UUID within its millisecond
+    pub sequence: u16,
+
+    /// Unix timestamp (in milliseconds) of the last issued UUID
+    pub timestamp: u64,
+
+    /// How to wait for the next millisecond when the counter is exhausted
+    pub wait_strategy: WaitStrategy,
+
+    /// How to react when the clock goes backwards
+    pub rollback_policy: RollbackPolicy,
+
+    /// Source of the current time
+    pub clock: C,
+
+    random: Random,
+}
+
+impl UuidV7Generator {
+    /// Create a new UUIDv7 generator
+    pub fn new() -> Self {
+        Self {
+            sequence: 0,
+            timestamp: 0,
+            wait_strategy: WaitStrategy::default(),
+            rollback_policy: RollbackPolicy::default(),
+            clock: SystemClock,
+            random: Random::new(),
+        }
+    }
+}
+
+impl Default for UuidV7Generator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: Clock> UuidV7Generator<C> {
+    /// Use a different source of time for the generator
+    pub fn with_clock<D: Clock>(self, clock: D) -> UuidV7Generator<D> {
+        UuidV7Generator {
+            sequence: 0,
+            timestamp: 0,
+            wait_strategy: self.wait_strategy,
+            rollback_policy: self.rollback_policy,
+            clock,
+            random: self.random,
+        }
+    }
+
+    /// Set how the generator waits when the counter is exhausted
+    pub fn with_wait_strategy(mut self, wait_strategy: WaitStrategy) -> Self {
+        self.wait_strategy = wait_strategy;
+        self
+    }
+
+    /// Set how the generator reacts when the clock goes backwards
+    pub fn with_rollback_policy(mut self, rollback_policy: RollbackPolicy) -> Self {
+        self.rollback_policy = rollback_policy;
+        self
+    }
+
+    /// Generate a UUIDv7 as a 128-bit integer
+    ///
+    /// Waits and fails in the same way as [`IdGenerator::generate_id`](crate::IdGenerator::generate_id).
+    pub fn generate_id(&mut self) -> Result<u128, Error> {
+        loop {
+            match self.try_generate_id() {
+                Ok(uuid) => return Ok(uuid),
+                Err(err) => state::recover(
+                    &self.clock,
+                    &UUID_LAYOUT,
+                    0,
+                    self.wait_strategy,
+                    self.rollback_policy,
+                    err,
+                )?,
+            }
+        }
+    }
+
+    /// Generate a UUIDv7 as a 128-bit integer without blocking
+    ///
+    /// Fails in the same way as [`IdGenerator::try_generate_id`](crate::IdGenerator::try_generate_id).
+    pub fn try_generate_id(&mut self) -> Result<u128, Error> {
+        let now = self.clock.now_millis();
+        (self.timestamp, self.sequence) = state::advance(
+            &UUID_LAYOUT,
+            self.rollback_policy,
+            0,
+            self.timestamp,
+            self.sequence,
+            now,
+        )?;
+
+        let rand_b = self.random.next_u64() as u128 & ((1 << 62) - 1);
+        Ok(((self.timestamp as u128) << 80)
+            | VERSION
+            | (self.sequence as u128) << 64
+            | VARIANT
+            | rand_b)
+    }
+
+    /// Generate a UUIDv7
+    #[cfg(feature = "uuid")]
+    pub fn generate_uuid(&mut self) -> Result<::uuid::Uuid, Error> {
+        self.generate_id().map(::uuid::Uuid::from_u128)
+    }
+}
+
+impl Chronoflake {
+    /// Embed the ID in a UUIDv7 that sorts in the same order
+    ///
+    /// The UUID's timestamp is the ID's Unix timestamp in milliseconds, and the
+    /// whole ID is kept in the `rand_a` and `rand_b` fields so it can be recovered
+    /// with [`from_uuid`](Self::from_uuid). Timestamps past the year 10889 do not
+    /// fit in the UUID and are capped, which keeps the order but not the time.
+    ///
+    /// ```rust
+    /// use chronoflake::Chronoflake;
+    ///
+    /// let id = Chronoflake::new(1704967240656416804).with_epoch(1488432924251);
+    /// let uuid = id.to_uuid();
+    /// assert_eq!((uuid >> 80) as u64, id.timestamp());
+    /// assert_eq!(Chronoflake::from_uuid(uuid).unwrap().id(), id.id());
+    /// ```
+    pub fn to_uuid(&self) -> u128 {
+        let timestamp = self.timestamp().min(MAX_UNIX_MILLIS) as u128;
+        let id = (self.id() as u128) << EMBED_PADDING;
+        let rand_a = id >> 62;
+        let rand_b = id & ((1 << 62) - 1);
+        (timestamp << 80) | VERSION | (rand_a << 64) | VARIANT | rand_b
+    }
+
+    /// Recover an ID embedded with [`to_uuid`](Self::to_uuid)
+    ///
+    /// The ID has the default epoch and layout, use [`with_epoch`](Self::with_epoch)
+    /// and [`with_layout`](Self::with_layout) to decode it. Fails with
+    /// [`Error::NotAnEmbeddedId`] if the UUID is not version 7 or has bits set
+    /// where `to_uuid` leaves none.
+    pub fn from_uuid(uuid: u128) -> Result<Self, Error> {
+        let padding = (1 << EMBED_PADDING) - 1;
+        if uuid & VERSION_MASK != VERSION || uuid & VARIANT_MASK != VARIANT || uuid & padding != 0 {
+            return Err(Error::NotAnEmbeddedId);
+        }
+
+        let rand_a = (uuid >> 64) & 0xFFF;
+        let rand_b = uuid & ((1 << 62) - 1);
+        let id = ((rand_a << 62) | rand_b) >> EMBED_PADDING;
+        Ok(Self::new(id as u64))
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<Chronoflake> for ::uuid::Uuid {
+    fn from(id: Chronoflake) -> Self {
+        Self::from_u128(id.to_uuid())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl TryFrom<::uuid::Uuid> for Chronoflake {
+    type Error = Error;
+
+    fn try_from(uuid: ::uuid::Uuid) -> Result<Self, Self::Error> {
+        Self::from_uuid(uuid.as_u128())
+    }
+}
+
+/// Write a UUID in the usual hyphenated lower case hex form
+pub(crate) fn hyphenated(uuid: u128) -> String {
+    let hex = format!("{uuid:032x}");
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+/// Parse a UUID written as 32 hex digits, optionally hyphenated
+pub(crate) fn parse_hyphenated(s: &str) -> Result<u128, Error> {
+    let length = s.chars().count();
+    let hyphens = [8, 13, 18, 23];
+    let hyphenated = length == 36 && hyphens.iter().all(|&i| s.as_bytes()[i] == b'-');
+    if length != 32 && !hyphenated {
+        return Err(Error::InvalidLength { length, max: 36 });
+    }
+
+    s.chars()
+        .enumerate()
+        .filter(|&(position, _)| !(hyphenated && hyphens.contains(&position)))
+        .try_fold(0u128, |uuid, (position, character)| {
+            let digit = character.to_digit(16).ok_or(Error::InvalidCharacter {
+                character,
+                position,
+            })?;
+            Ok(uuid << 4 | digit as u128)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use crate::{IdGenerator, MockClock, DEFAULT_EPOCH};
+
+    #[test]
+    fn uuid_fields() {
+        let clock = MockClock::new(0x0123_4567_89AB);
+        let mut generator = UuidV7Generator::new().with_clock(clock.clone());
+
+        let uuids: Vec<u128> = (0..5000)
+            .map(|_| generator.generate_id().unwrap())
+            .collect();
+        assert!(uuids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(uuids.iter().collect::<HashSet<_>>().len(), 5000);
+
+        for uuid in &uuids {
+            assert_eq!(uuid & VERSION_MASK, VERSION);
+            assert_eq!(uuid & VARIANT_MASK, VARIANT);
+        }
+
+        // The counter runs out after 4096 UUIDs and moves on to the next millisecond
+        assert_eq!(uuids[0] >> 80, 0x0123_4567_89AB);
+        assert_eq!((uuids[4095] >> 64) & 0xFFF, 4095);
+        assert_eq!(uuids[4096] >> 80, 0x0123_4567_89AC);
+        assert_eq!((uuids[4096] >> 64) & 0xFFF, 0);
+        assert_ne!(uuids[0] & 0xFFFF_FFFF, uuids[1] & 0xFFFF_FFFF);
+    }
+
+    #[test]
+    fn clones_issue_different_uuids() {
+        let clock = MockClock::new(0x0123_4567_89AB);
+        let mut generator = UuidV7Generator::new().with_clock(clock);
+        generator.generate_id().unwrap();
+
+        let mut clone = generator.clone();
+        for _ in 0..10 {
+            assert_ne!(generator.generate_id(), clone.generate_id());
+        }
+    }
+
+    #[test]
+    fn rollback_policy_applies() {
+        let clock = MockClock::new(1_000_000);
+        let mut generator = UuidV7Generator::new()
+            .with_clock(clock.clone())
+            .with_rollback_policy(RollbackPolicy::Error);
+
+        generator.generate_id().unwrap();
+        clock.rewind(Duration::from_millis(5));
+        assert_eq!(
+            generator.generate_id(),
+            Err(Error::ClockMovedBackwards { by: 5 })
+        );
+    }
+
+    #[test]
+    fn embedding_round_trips_and_sorts() {
+        let clock = MockClock::new(DEFAULT_EPOCH + 1000);
+        let mut cf = IdGenerator::new(1023).with_clock(clock.clone());
+
+        let mut ids = cf.generate_batch(5000).unwrap();
+        clock.advance(Duration::from_secs(3600));
+        ids.extend(cf.generate_batch(10).unwrap());
+        ids.push(i64::MAX as u64);
+
+        let uuids: Vec<u128> = ids.iter().map(|&id| cf.decode(id).to_uuid()).collect();
+        assert!(uuids.windows(2).all(|w| w[0] < w[1]));
+
+        for (&id, &uuid) in ids.iter().zip(&uuids) {
+            assert_eq!(uuid >> 80, cf.decode(id).timestamp() as u128);
+            assert_eq!(Chronoflake::from_uuid(uuid).unwrap().id(), id);
+        }
+    }
+
+    #[test]
+    fn rejects_other_uuids() {
+        let id = Chronoflake::new(1704967240656416804).to_uuid();
+        assert!(Chronoflake::from_uuid(id).is_ok());
+        assert_eq!(Chronoflake::from_uuid(id | 1), Err(Error::NotAnEmbeddedId));
+        assert_eq!(
+            Chronoflake::from_uuid(id & !VERSION_MASK | (4 << 76)),
+            Err(Error::NotAnEmbeddedId)
+        );
+
+        let random = UuidV7Generator::new().generate_id().unwrap();
+        let padding_clear = random & !((1 << EMBED_PADDING) - 1);
+        assert!(Chronoflake::from_uuid(padding_clear).is_ok());
+    }
+
+    #[test]
+    fn text_form() {
+        let uuid = 0x0190_e0a1_2b3c_7def_8123_4567_89ab_cdef;
+        let text = hyphenated(uuid);
+        assert_eq!(text, "0190e0a1-2b3c-7def-8123-456789abcdef");
+        assert_eq!(parse_hyphenated(&text), Ok(uuid));
+        assert_eq!(parse_hyphenated(&text.to_uppercase()), Ok(uuid));
+        assert_eq!(parse_hyphenated(&text.replace('-', "")), Ok(uuid));
+
+        assert!(matches!(
+            parse_hyphenated("0190e0a1-2b3c-7def-8123-456789abcde"),
+            Err(Error::InvalidLength { .. })
+        ));
+        assert_eq!(
+            parse_hyphenated("0190e0a1-2b3c-7def-8123-456789abcdeg"),
+            Err(Error::InvalidCharacter {
+                character: 'g',
+                position: 35
+            })
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_crate_conversions() {
+        let id = Chronoflake::new(1704967240656416804);
+        let uuid = ::uuid::Uuid::from(id);
+        assert_eq!(uuid.get_version_num(), 7);
+        assert_eq!(Chronoflake::try_from(uuid).unwrap(), id);
+
+        let uuid = UuidV7Generator::new().generate_uuid().unwrap();
+        assert_eq!(uuid.get_version(), Some(::uuid::Version::SortRand));
+        assert!(uuid.get_timestamp().is_some());
+    }
+}